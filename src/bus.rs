@@ -0,0 +1,111 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+#[cfg(test)]
+use std::cell::RefCell;
+
+use crate::memory::Memory;
+
+#[cfg(test)]
+const FLAT_RAM_SIZE: usize = 0x10000;
+
+// Everything the CPU can reach: the address space, which includes IE and
+// IF, plus the interrupt master enable which isn't memory mapped
+pub trait Bus: Memory {
+    fn ime(&self) -> bool;
+
+    fn set_ime(&mut self, ime: bool);
+}
+
+// 64KiB of plain RAM with nothing mapped anywhere, for running the CPU on
+// its own
+#[cfg(test)]
+pub struct FlatRam {
+    ram: Vec<u8>,
+    ime: bool,
+    accesses: RefCell<Option<Vec<Access>>>,
+}
+
+// a single read or write of (address, value) seen by FlatRam
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read(u16, u8),
+    Write(u16, u8),
+}
+
+#[cfg(test)]
+impl Memory for FlatRam {
+    fn mem_read_byte(&self, addr: u16) -> u8 {
+        let val = self.ram[addr as usize];
+        self.log(Access::Read(addr, val));
+        val
+    }
+
+    fn mem_write_byte(&mut self, addr: u16, val: u8) {
+        self.ram[addr as usize] = val;
+        self.log(Access::Write(addr, val));
+    }
+}
+
+#[cfg(test)]
+impl Bus for FlatRam {
+    fn ime(&self) -> bool {
+        self.ime
+    }
+
+    fn set_ime(&mut self, ime: bool) {
+        self.ime = ime;
+    }
+}
+
+#[cfg(test)]
+impl Default for FlatRam {
+    fn default() -> FlatRam {
+        FlatRam::new()
+    }
+}
+
+#[cfg(test)]
+impl FlatRam {
+    pub fn new() -> FlatRam {
+        FlatRam {
+            ram: vec![0; FLAT_RAM_SIZE],
+            ime: false,
+            accesses: RefCell::new(None),
+        }
+    }
+
+    // keep a log of every read and write for take_accesses, e.g. to check
+    // the bus activity of an instruction
+    pub fn log_accesses(&mut self, log: bool) {
+        *self.accesses.get_mut() = if log { Some(Vec::new()) } else { None };
+    }
+
+    // each access since the last call, in order
+    pub fn take_accesses(&mut self) -> Vec<Access> {
+        self.accesses.get_mut().as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn log(&self, access: Access) {
+        if let Some(accesses) = self.accesses.borrow_mut().as_mut() {
+            accesses.push(access);
+        }
+    }
+
+    // copy data to the start of RAM
+    pub fn load(&mut self, data: &[u8]) {
+        self.ram[..data.len()].copy_from_slice(data);
+    }
+}
@@ -0,0 +1,71 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+// Memory bank controller. Cartridge owns ROM and RAM, the MBC only decides
+// which part of them is visible at a given address.
+pub trait Mbc: Send {
+    // translate an address in 0x0000..=0x7fff to an offset into ROM
+    fn rom_offset(&self, addr: u16) -> usize;
+
+    // translate an address in 0xa000..=0xbfff to an offset into RAM,
+    // None if RAM is not accessible
+    fn ram_offset(&self, addr: u16) -> Option<usize>;
+
+    // handle a write to the MBC registers in 0x0000..=0x7fff
+    fn write_reg(&mut self, addr: u16, val: u8);
+
+    // some MBCs map their own registers into 0xa000..=0xbfff in place of
+    // RAM (e.g. the MBC3 clock). None means the access goes to RAM
+    fn read_mapped(&self, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    // returns true if the write was handled by the MBC instead of RAM
+    fn write_mapped(&mut self, _addr: u16, _val: u8) -> bool {
+        false
+    }
+
+    // state of the rumble motor, for carts that have one
+    fn rumble(&self) -> bool {
+        false
+    }
+
+    // extra state stored after RAM in the .sav file
+    fn save_trailer(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn load_save_trailer(&mut self, _data: &[u8]) {
+        // do nothing
+    }
+}
+
+// Plain 32KiB ROM with optional 8KiB RAM and no banking
+pub struct RomOnly;
+
+impl Mbc for RomOnly {
+    fn rom_offset(&self, addr: u16) -> usize {
+        addr as usize
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        Some((addr as usize) - 0xa000)
+    }
+
+    fn write_reg(&mut self, _addr: u16, _val: u8) {
+        // do nothing
+    }
+}
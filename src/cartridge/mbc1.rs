@@ -0,0 +1,106 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use crate::cartridge::mbc::{Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+/*
+ * MBC1
+ *
+ * 0000..1FFF: RAM enable, 0x0A in the lower nibble enables
+ * 2000..3FFF: BANK1, lower 5 bits of the ROM bank number. 0 is treated as 1
+ * 4000..5FFF: BANK2, 2 bits used as either upper ROM bank bits or RAM bank
+ * 6000..7FFF: banking mode select
+ *
+ * In mode 0 BANK2 only affects 4000..7FFF. In mode 1 it is also applied to
+ * 0000..3FFF and selects the RAM bank.
+ *
+ * MBC1M multicarts wire BANK2 to ROM address bits 18-19 instead of 19-20,
+ * so only the lower 4 bits of BANK1 are used.
+ * https://gbdev.io/pandocs/MBC1.html
+ */
+pub struct Mbc1 {
+    ram_enabled: bool,
+    bank1: u8,
+    bank2: u8,
+    mode: u8,
+    multicart: bool,
+}
+
+impl Mbc for Mbc1 {
+    fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3fff => {
+                if self.mode == 1 {
+                    self.upper_bits()
+                } else {
+                    0
+                }
+            },
+            _ => self.upper_bits() | self.lower_bits(),
+        };
+
+        bank * ROM_BANK_SIZE + ((addr as usize) & (ROM_BANK_SIZE - 1))
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+
+        let bank = if self.mode == 1 { self.bank2 as usize } else { 0 };
+        Some(bank * RAM_BANK_SIZE + ((addr as usize) & (RAM_BANK_SIZE - 1)))
+    }
+
+    fn write_reg(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enabled = (val & 0x0f) == 0x0a,
+            0x2000..=0x3fff => {
+                // the zero check happens on all 5 bits, even on MBC1M
+                self.bank1 = val & 0x1f;
+                if self.bank1 == 0 {
+                    self.bank1 = 1;
+                }
+            },
+            0x4000..=0x5fff => self.bank2 = val & 0x03,
+            _ => self.mode = val & 0x01,
+        }
+    }
+}
+
+impl Mbc1 {
+    pub fn new(multicart: bool) -> Mbc1 {
+        Mbc1 {
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            mode: 0,
+            multicart,
+        }
+    }
+
+    fn lower_bits(&self) -> usize {
+        if self.multicart {
+            (self.bank1 & 0x0f) as usize
+        } else {
+            self.bank1 as usize
+        }
+    }
+
+    fn upper_bits(&self) -> usize {
+        if self.multicart {
+            (self.bank2 as usize) << 4
+        } else {
+            (self.bank2 as usize) << 5
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use crate::cartridge::mbc::{Mbc, ROM_BANK_SIZE};
+
+const MBC2_RAM_SIZE: usize = 512;
+const REG_SELECT_BIT: u16 = 8;
+
+/*
+ * MBC2
+ *
+ * 0000..3FFF: register select by address bit 8. When clear the write is
+ *             the RAM enable (0x0A enables), when set the lower 4 bits are
+ *             the ROM bank number, 0 is treated as 1
+ * A000..A1FF: 512x4 bit built-in RAM, echoed through BFFF
+ *
+ * Only the lower nibble of the built-in RAM exists, the upper nibble reads
+ * as 1s. Since the RAM lives in the MBC rather than on the cartridge it is
+ * kept here and saved one nibble per byte.
+ * https://gbdev.io/pandocs/MBC2.html
+ */
+pub struct Mbc2 {
+    ram_enabled: bool,
+    rom_bank: u8,
+    ram: [u8; MBC2_RAM_SIZE],
+}
+
+impl Mbc for Mbc2 {
+    fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3fff => 0,
+            _ => self.rom_bank as usize,
+        };
+
+        bank * ROM_BANK_SIZE + ((addr as usize) & (ROM_BANK_SIZE - 1))
+    }
+
+    fn ram_offset(&self, _addr: u16) -> Option<usize> {
+        // no external RAM, see read_mapped/write_mapped
+        None
+    }
+
+    fn write_reg(&mut self, addr: u16, val: u8) {
+        if addr > 0x3fff {
+            return;
+        }
+
+        if (addr & (1 << REG_SELECT_BIT)) == 0 {
+            self.ram_enabled = (val & 0x0f) == 0x0a;
+        } else {
+            self.rom_bank = val & 0x0f;
+            if self.rom_bank == 0 {
+                self.rom_bank = 1;
+            }
+        }
+    }
+
+    fn read_mapped(&self, addr: u16) -> Option<u8> {
+        if !self.ram_enabled {
+            return None;
+        }
+
+        Some(0xf0 | self.ram[(addr as usize) & (MBC2_RAM_SIZE - 1)])
+    }
+
+    fn write_mapped(&mut self, addr: u16, val: u8) -> bool {
+        if !self.ram_enabled {
+            return false;
+        }
+
+        self.ram[(addr as usize) & (MBC2_RAM_SIZE - 1)] = val & 0x0f;
+        true
+    }
+
+    fn save_trailer(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    fn load_save_trailer(&mut self, data: &[u8]) {
+        for (dst, src) in self.ram.iter_mut().zip(data.iter()) {
+            *dst = src & 0x0f;
+        }
+    }
+}
+
+impl Mbc2 {
+    pub fn new() -> Mbc2 {
+        Mbc2 {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram: [0; MBC2_RAM_SIZE],
+        }
+    }
+}
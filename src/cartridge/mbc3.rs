@@ -0,0 +1,331 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use std::convert::TryInto;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cartridge::mbc::{Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+const RTC_SAVE_SIZE: usize = 48;
+const RTC_SAVE_SIZE_32BIT: usize = 44;
+
+const DH_DAY_HIGH: u8 = 0;
+const DH_HALT: u8 = 6;
+const DH_CARRY: u8 = 7;
+
+// Wall clock used by the RTC, in seconds since the unix epoch. Shared
+// between every cartridge loaded, hence Sync
+pub trait Clock: Send + Sync {
+    fn now(&self) -> u64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct RtcRegs {
+    secs: u8,
+    mins: u8,
+    hours: u8,
+    days: u16,
+    halt: bool,
+    carry: bool,
+}
+
+impl RtcRegs {
+    fn read(&self, reg: u8) -> u8 {
+        match reg {
+            0x08 => self.secs,
+            0x09 => self.mins,
+            0x0a => self.hours,
+            0x0b => (self.days & 0xff) as u8,
+            _ => {
+                ((self.days >> 8) as u8 & 0x01) << DH_DAY_HIGH
+                    | (self.halt as u8) << DH_HALT
+                    | (self.carry as u8) << DH_CARRY
+            },
+        }
+    }
+
+    fn write(&mut self, reg: u8, val: u8) {
+        match reg {
+            0x08 => self.secs = val & 0x3f,
+            0x09 => self.mins = val & 0x3f,
+            0x0a => self.hours = val & 0x1f,
+            0x0b => self.days = (self.days & 0x100) | (val as u16),
+            _ => {
+                self.days = (self.days & 0xff) | (((val >> DH_DAY_HIGH) as u16 & 0x01) << 8);
+                self.halt = (val & (1 << DH_HALT)) != 0;
+                self.carry = (val & (1 << DH_CARRY)) != 0;
+            },
+        }
+    }
+
+    fn advance(&mut self, mut secs: u64) {
+        if self.halt {
+            return;
+        }
+
+        // out of range values count up to the register width and wrap
+        // without carrying, do those the slow way
+        while secs > 0 && (self.secs >= 60 || self.mins >= 60 || self.hours >= 24) {
+            self.tick();
+            secs -= 1;
+        }
+
+        let total = (self.secs as u64)
+            + (self.mins as u64) * 60
+            + (self.hours as u64) * 3600
+            + (self.days as u64) * 86400
+            + secs;
+
+        self.secs = (total % 60) as u8;
+        self.mins = ((total / 60) % 60) as u8;
+        self.hours = ((total / 3600) % 24) as u8;
+
+        let days = total / 86400;
+        if days > 0x1ff {
+            self.carry = true;
+        }
+        self.days = (days & 0x1ff) as u16;
+    }
+
+    fn tick(&mut self) {
+        self.secs += 1;
+        match self.secs {
+            60 => self.secs = 0,
+            64 => { self.secs = 0; return; },
+            _ => return,
+        }
+
+        self.mins += 1;
+        match self.mins {
+            60 => self.mins = 0,
+            64 => { self.mins = 0; return; },
+            _ => return,
+        }
+
+        self.hours += 1;
+        match self.hours {
+            24 => self.hours = 0,
+            32 => { self.hours = 0; return; },
+            _ => return,
+        }
+
+        self.days += 1;
+        if self.days > 0x1ff {
+            self.days = 0;
+            self.carry = true;
+        }
+    }
+
+    // registers are stored as 5 little endian u32s, DH in the last one
+    fn serialize(&self, data: &mut Vec<u8>) {
+        for reg in 0x08..=0x0c {
+            data.extend_from_slice(&(self.read(reg) as u32).to_le_bytes());
+        }
+    }
+
+    fn deserialize(data: &[u8]) -> RtcRegs {
+        let mut regs = RtcRegs::default();
+        for (i, reg) in (0x08..=0x0c).enumerate() {
+            regs.write(reg, data[i * 4]);
+        }
+        regs
+    }
+}
+
+/*
+ * MBC3 Real Time Clock
+ *
+ * The clock runs off the host wall clock. Rather than ticking every cycle
+ * the registers are brought up to date whenever they are latched, written
+ * or saved, so the clock keeps running while the emulator is closed.
+ *
+ * The save format is the 48 byte trailer used by VBA-M/BGB/SameBoy:
+ * current registers, latched registers, then a 64 bit unix timestamp.
+ */
+pub struct Rtc {
+    clock: Arc<dyn Clock>,
+    regs: RtcRegs,
+    latched: RtcRegs,
+    last_update: u64,
+}
+
+impl Rtc {
+    pub fn new(clock: Arc<dyn Clock>) -> Rtc {
+        let now = clock.now();
+
+        Rtc {
+            clock,
+            regs: RtcRegs::default(),
+            latched: RtcRegs::default(),
+            last_update: now,
+        }
+    }
+
+    fn update(&mut self) {
+        self.regs = self.current();
+        self.last_update = self.clock.now();
+    }
+
+    fn current(&self) -> RtcRegs {
+        let mut regs = self.regs;
+        regs.advance(self.clock.now().saturating_sub(self.last_update));
+        regs
+    }
+
+    fn latch(&mut self) {
+        self.update();
+        self.latched = self.regs;
+    }
+
+    fn read(&self, reg: u8) -> u8 {
+        self.latched.read(reg)
+    }
+
+    fn write(&mut self, reg: u8, val: u8) {
+        self.update();
+        self.regs.write(reg, val);
+        self.latched.write(reg, val);
+    }
+}
+
+/*
+ * MBC3
+ *
+ * 0000..1FFF: RAM and RTC enable, 0x0A in the lower nibble enables
+ * 2000..3FFF: 7 bit ROM bank number, 0 is treated as 1
+ * 4000..5FFF: 0x00-0x03 selects a RAM bank, 0x08-0x0C an RTC register
+ * 6000..7FFF: writing 0x00 then 0x01 latches the RTC registers
+ * https://gbdev.io/pandocs/MBC3.html
+ */
+pub struct Mbc3 {
+    ram_enabled: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    latch_reg: u8,
+    rtc: Option<Rtc>,
+}
+
+impl Mbc for Mbc3 {
+    fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3fff => 0,
+            _ => self.rom_bank as usize,
+        };
+
+        bank * ROM_BANK_SIZE + ((addr as usize) & (ROM_BANK_SIZE - 1))
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram_bank > 0x03 {
+            return None;
+        }
+
+        Some((self.ram_bank as usize) * RAM_BANK_SIZE + ((addr as usize) & (RAM_BANK_SIZE - 1)))
+    }
+
+    fn write_reg(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enabled = (val & 0x0f) == 0x0a,
+            0x2000..=0x3fff => {
+                self.rom_bank = val & 0x7f;
+                if self.rom_bank == 0 {
+                    self.rom_bank = 1;
+                }
+            },
+            0x4000..=0x5fff => self.ram_bank = val & 0x0f,
+            _ => {
+                if self.latch_reg == 0x00 && val == 0x01 {
+                    if let Some(rtc) = &mut self.rtc {
+                        rtc.latch();
+                    }
+                }
+                self.latch_reg = val;
+            },
+        }
+    }
+
+    fn read_mapped(&self, _addr: u16) -> Option<u8> {
+        match &self.rtc {
+            Some(rtc) if self.rtc_selected() => Some(rtc.read(self.ram_bank)),
+            _ => None,
+        }
+    }
+
+    fn write_mapped(&mut self, _addr: u16, val: u8) -> bool {
+        let selected = self.rtc_selected();
+
+        match &mut self.rtc {
+            Some(rtc) if selected => {
+                rtc.write(self.ram_bank, val);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn save_trailer(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        if let Some(rtc) = &self.rtc {
+            rtc.current().serialize(&mut data);
+            rtc.latched.serialize(&mut data);
+            data.extend_from_slice(&rtc.clock.now().to_le_bytes());
+        }
+
+        data
+    }
+
+    fn load_save_trailer(&mut self, data: &[u8]) {
+        if let Some(rtc) = &mut self.rtc {
+            // some emulators only write a 32 bit timestamp
+            let timestamp = match data.len() {
+                RTC_SAVE_SIZE => u64::from_le_bytes(data[40..48].try_into().unwrap()),
+                RTC_SAVE_SIZE_32BIT => u32::from_le_bytes(data[40..44].try_into().unwrap()) as u64,
+                _ => return,
+            };
+
+            rtc.regs = RtcRegs::deserialize(&data[0..20]);
+            rtc.latched = RtcRegs::deserialize(&data[20..40]);
+            rtc.last_update = timestamp;
+            rtc.update();
+        }
+    }
+}
+
+impl Mbc3 {
+    pub fn new(rtc: Option<Rtc>) -> Mbc3 {
+        Mbc3 {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            latch_reg: 0xff,
+            rtc,
+        }
+    }
+
+    fn rtc_selected(&self) -> bool {
+        self.ram_enabled && (0x08..=0x0c).contains(&self.ram_bank)
+    }
+}
@@ -0,0 +1,89 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use crate::cartridge::mbc::{Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+const RUMBLE_MOTOR: u8 = 3;
+
+/*
+ * MBC5
+ *
+ * 0000..1FFF: RAM enable, 0x0A enables
+ * 2000..2FFF: lower 8 bits of the ROM bank number
+ * 3000..3FFF: bit 8 of the ROM bank number
+ * 4000..5FFF: RAM bank number 0x00-0x0F
+ *
+ * Unlike the older MBCs bank 0 can be mapped into 4000..7FFF. On rumble
+ * carts bit 3 of the RAM bank register drives the motor instead, leaving
+ * only 8 RAM banks.
+ * https://gbdev.io/pandocs/MBC5.html
+ */
+pub struct Mbc5 {
+    ram_enabled: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    has_rumble: bool,
+    rumble: bool,
+}
+
+impl Mbc for Mbc5 {
+    fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3fff => 0,
+            _ => self.rom_bank as usize,
+        };
+
+        bank * ROM_BANK_SIZE + ((addr as usize) & (ROM_BANK_SIZE - 1))
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+
+        Some((self.ram_bank as usize) * RAM_BANK_SIZE + ((addr as usize) & (RAM_BANK_SIZE - 1)))
+    }
+
+    fn write_reg(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enabled = val == 0x0a,
+            0x2000..=0x2fff => self.rom_bank = (self.rom_bank & 0x100) | (val as u16),
+            0x3000..=0x3fff => self.rom_bank = (self.rom_bank & 0xff) | (((val & 0x01) as u16) << 8),
+            0x4000..=0x5fff => {
+                if self.has_rumble {
+                    self.rumble = (val & (1 << RUMBLE_MOTOR)) != 0;
+                    self.ram_bank = val & 0x07;
+                } else {
+                    self.ram_bank = val & 0x0f;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+}
+
+impl Mbc5 {
+    pub fn new(has_rumble: bool) -> Mbc5 {
+        Mbc5 {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumble: false,
+        }
+    }
+}
@@ -11,13 +11,100 @@
 // ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
 // ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
 
+mod mbc;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+// shared with the integration tests
+#[cfg(test)]
+#[path = "../../tests/common/rom.rs"]
+mod testing;
+
+use std::error;
+use std::fmt;
 use std::fs;
 use std::io;
+use std::path::Path;
+use std::sync::Arc;
 
+use crate::cartridge::mbc::{Mbc, RomOnly, ROM_BANK_SIZE};
+use crate::cartridge::mbc1::Mbc1;
+use crate::cartridge::mbc2::Mbc2;
+use crate::cartridge::mbc3::{Mbc3, Rtc, SystemClock};
+
+pub use crate::cartridge::mbc3::Clock;
+use crate::cartridge::mbc5::Mbc5;
 use crate::memory::Memory;
 
 const HEADER_START: usize = 0x100;
 const HEADER_SIZE: usize = 80;
+const MBC1M_ROM_SIZE: usize = 0x100000;
+
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b,
+    0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00, 0x0d,
+    0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e,
+    0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99,
+    0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc,
+    0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e
+];
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    MissingHeader,
+    HeaderChecksum { expected: u8, actual: u8 },
+    GlobalChecksum { expected: u16, actual: u16 },
+    InvalidLogo,
+    InvalidCartridgeType(u8),
+    UnsupportedCartridgeType(u8),
+    InvalidRomSize(u8),
+    InvalidRamSize(u8),
+    Mbc2RamSize(usize),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartridgeError::Io(e) => write!(f, "{}", e),
+            CartridgeError::MissingHeader => write!(f, "file is too small to contain a cartridge header"),
+            CartridgeError::HeaderChecksum { expected, actual } => {
+                write!(f, "bad header checksum: expected {:#04x}, calculated {:#04x}", expected, actual)
+            },
+            CartridgeError::GlobalChecksum { expected, actual } => {
+                write!(f, "bad global checksum: expected {:#06x}, calculated {:#06x}", expected, actual)
+            },
+            CartridgeError::InvalidLogo => write!(f, "Nintendo logo does not match"),
+            CartridgeError::InvalidCartridgeType(t) => write!(f, "invalid cartridge type: {:#04x}", t),
+            CartridgeError::UnsupportedCartridgeType(t) => write!(f, "unsupported cartridge type: {:#04x}", t),
+            CartridgeError::InvalidRomSize(code) => write!(f, "invalid ROM size code: {:#04x}", code),
+            CartridgeError::InvalidRamSize(code) => write!(f, "invalid RAM size code: {:#04x}", code),
+            CartridgeError::Mbc2RamSize(size) => {
+                write!(f, "RAM size must be 0 when using MBC2 chip, header says {} bytes", size)
+            },
+        }
+    }
+}
+
+impl error::Error for CartridgeError {}
+
+impl From<io::Error> for CartridgeError {
+    fn from(e: io::Error) -> CartridgeError {
+        CartridgeError::Io(e)
+    }
+}
+
+// Notified whenever the cartridge turns its rumble motor on or off
+pub trait Rumble: Send {
+    fn set_rumble(&mut self, on: bool);
+}
+
+impl<F: FnMut(bool) + Send> Rumble for F {
+    fn set_rumble(&mut self, on: bool) {
+        self(on)
+    }
+}
 
 #[derive(Debug)]
 enum CartridgeType {
@@ -51,6 +138,23 @@ enum CartridgeType {
     HuC1RamBattery = 0xff,
 }
 
+impl CartridgeType {
+    fn has_battery(&self) -> bool {
+        matches!(self,
+                 CartridgeType::Mbc1RamBattery
+                 | CartridgeType::Mbc2Battery
+                 | CartridgeType::RomRamBattery
+                 | CartridgeType::Mmm01RamBattery
+                 | CartridgeType::Mbc3TimerBattery
+                 | CartridgeType::Mbc3TimerRamBattery
+                 | CartridgeType::Mbc3RamBattery
+                 | CartridgeType::Mbc5RamBattery
+                 | CartridgeType::Mbc5RumbleRamBattery
+                 | CartridgeType::Mbc7SensorRumbleRamBattery
+                 | CartridgeType::HuC1RamBattery)
+    }
+}
+
 #[derive(Debug)]
 enum CartridgeMode {
     PgbMode,
@@ -93,6 +197,14 @@ impl Header {
 pub struct Cartridge {
     header: Header,
     rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_dirty: bool,
+    mbc: Box<dyn Mbc>,
+    rumble: bool,
+    rumble_handler: Option<Box<dyn Rumble>>,
+    clock: Arc<dyn Clock>,
+    lenient: bool,
+    warnings: Vec<CartridgeError>,
     loaded: bool,
 }
 
@@ -101,17 +213,44 @@ impl Cartridge {
         Cartridge {
             header: Header::new(),
             rom: Vec::new(),
+            ram: Vec::new(),
+            ram_dirty: false,
+            mbc: Box::new(RomOnly),
+            rumble: false,
+            rumble_handler: None,
+            clock: Arc::new(SystemClock),
+            lenient: false,
+            warnings: Vec::new(),
             loaded: false,
         }
     }
 
-    pub fn load_rom(&mut self, path: String) -> Result<(), io::Error> {
-        self.rom = fs::read(path)?;
+    // In lenient mode a bad global checksum or Nintendo logo is only
+    // warned about. Homebrew and patched ROMs often get these wrong, and
+    // real hardware never checks the global checksum anyway.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    // problems lenient mode let through when the last ROM was loaded
+    pub fn warnings(&self) -> &[CartridgeError] {
+        &self.warnings
+    }
+
+    pub fn load_rom_data(&mut self, data: Vec<u8>) -> Result<(), CartridgeError> {
+        self.loaded = false;
+        self.warnings.clear();
+        self.rom = data;
+        self.mbc = Box::new(RomOnly);
         if self.rom.len() < (HEADER_START + HEADER_SIZE) {
-            panic!("missing header");
+            return Err(CartridgeError::MissingHeader);
         }
 
-        self.parse_header();
+        self.parse_header()?;
+        self.ram = vec![0; self.header.ram_size];
+        self.ram_dirty = false;
+        self.mbc = self.create_mbc()?;
+        self.set_rumble(false);
         self.loaded = true;
 
         #[cfg(debug_assertions)]
@@ -125,27 +264,115 @@ impl Cartridge {
         self.loaded
     }
 
-    fn parse_header(&mut self) {
+    pub fn set_rumble_handler(&mut self, handler: Box<dyn Rumble>) {
+        self.rumble_handler = Some(handler);
+    }
+
+    // wall clock for MBC3 RTCs, used by ROMs loaded after this is set
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = Arc::from(clock);
+    }
+
+    fn set_rumble(&mut self, on: bool) {
+        if on != self.rumble {
+            self.rumble = on;
+            if let Some(handler) = &mut self.rumble_handler {
+                handler.set_rumble(on);
+            }
+        }
+    }
+
+    pub fn is_cgb(&self) -> bool {
+        matches!(self.header.cart_mode, CartridgeMode::CgbSupported | CartridgeMode::CgbOnly)
+    }
+
+    // whether RAM (and RTC) contents should be persisted between runs
+    pub fn has_battery(&self) -> bool {
+        self.header.cart_type.has_battery()
+    }
+
+    // whether RAM or MBC registers have been written since the last load
+    // or save
+    pub fn is_ram_dirty(&self) -> bool {
+        self.ram_dirty
+    }
+
+    // Saves are a raw dump of cartridge RAM, the same format used by most
+    // other emulators. A missing save file is not an error, the game just
+    // starts with empty RAM. A save of the wrong size is loaded as far as
+    // it fits. Anything past the end of RAM belongs to the MBC.
+    pub fn load_save(&mut self, path: &Path) -> Result<(), io::Error> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let len = data.len().min(self.ram.len());
+        self.ram[..len].copy_from_slice(&data[..len]);
+        self.mbc.load_save_trailer(&data[len..]);
+        self.ram_dirty = false;
+
+        Ok(())
+    }
+
+    pub fn write_save(&mut self, path: &Path) -> Result<(), io::Error> {
+        let mut data = self.ram.clone();
+        data.extend(self.mbc.save_trailer());
+
+        fs::write(path, data)?;
+        self.ram_dirty = false;
+
+        Ok(())
+    }
+
+    // report a problem that lenient mode lets slide
+    fn check_lenient(&mut self, err: CartridgeError) -> Result<(), CartridgeError> {
+        if self.lenient {
+            self.warnings.push(err);
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    fn parse_header(&mut self) -> Result<(), CartridgeError> {
         self.header.header_checksum = self.mem_read_byte(0x14d);
-        assert_eq!(self.header.header_checksum, self.calc_header_checksum());
+        let header_checksum = self.calc_header_checksum();
+        if self.header.header_checksum != header_checksum {
+            return Err(CartridgeError::HeaderChecksum {
+                expected: self.header.header_checksum,
+                actual: header_checksum,
+            });
+        }
 
         self.header.cart_checksum = self.mem_read_word_be(0x14e);
-        assert_eq!(self.header.cart_checksum, self.calc_cart_checksum());
+        let cart_checksum = self.calc_cart_checksum();
+        if self.header.cart_checksum != cart_checksum {
+            self.check_lenient(CartridgeError::GlobalChecksum {
+                expected: self.header.cart_checksum,
+                actual: cart_checksum,
+            })?;
+        }
 
-        assert!(self.compare_nintendo_logo());
+        if !self.compare_nintendo_logo(0) {
+            self.check_lenient(CartridgeError::InvalidLogo)?;
+        }
 
-        self.header.title = String::from_utf8(self.rom[0x134..0x143].to_vec()).unwrap();
+        self.header.title = String::from_utf8_lossy(&self.rom[0x134..0x143]).into_owned();
 
-        // default to PGB mode, so don't bother reading bit 7
-        if self.mem_is_set(0x143, 6) {
+        // bit 7 clear is an old DMG cart, where 0x143 is still part of the
+        // title. treat those the same as PGB mode
+        if !self.mem_is_set(0x143, 7) || self.mem_is_set(0x143, 3) || self.mem_is_set(0x143, 2) {
+            self.header.cart_mode = CartridgeMode::PgbMode;
+        } else if self.mem_is_set(0x143, 6) {
             self.header.cart_mode = CartridgeMode::CgbOnly;
-        } else if !(self.mem_is_set(0x143, 3) || self.mem_is_set(0x143, 2)) {
-            self.header.cart_mode = CartridgeMode::CgbSupported;
         } else {
-            self.header.cart_mode = CartridgeMode::PgbMode;
+            self.header.cart_mode = CartridgeMode::CgbSupported;
         }
 
-       self.header.cart_type = match self.mem_read_byte(0x147) {
+       let cart_type = self.mem_read_byte(0x147);
+       self.header.cart_type = match cart_type {
             0x00 => CartridgeType::RomOnly,
             0x01 => CartridgeType::Mbc1,
             0x02 => CartridgeType::Mbc1Ram,
@@ -174,7 +401,7 @@ impl Cartridge {
             0xfd => CartridgeType::BandaiTama5,
             0xfe => CartridgeType::HuC3,
             0xff => CartridgeType::HuC1RamBattery,
-            _ => panic!("invalid cartridge type"),
+            _ => return Err(CartridgeError::InvalidCartridgeType(cart_type)),
         };
 
         let rom_size_code = self.mem_read_byte(0x148);
@@ -188,17 +415,18 @@ impl Cartridge {
          * these values is unknown."
          */
         if rom_size_code > 0x08 {
-            panic!("invalid ROM size");
+            return Err(CartridgeError::InvalidRomSize(rom_size_code));
         }
         self.header.rom_size = 32768 << rom_size_code;
 
-        self.header.ram_size = match self.mem_read_byte(0x149) {
+        let ram_size_code = self.mem_read_byte(0x149);
+        self.header.ram_size = match ram_size_code {
             0x00 => 0, // no RAM
             0x02 => 8192, // 8KiB
             0x03 => 32768, // 32KiB
             0x04 => 131072, // 128KiB
             0x05 => 65536, // 64KiB
-            _ => panic!("invalid RAM size"),
+            _ => return Err(CartridgeError::InvalidRamSize(ram_size_code)),
         };
 
         // When using a MBC2 chip, 0x00 must be specified as the RAM Size, even though the MBC2
@@ -206,27 +434,49 @@ impl Cartridge {
         if (matches!(self.header.cart_type, CartridgeType::Mbc2)
             || matches!(self.header.cart_type, CartridgeType::Mbc2Battery))
            && self.header.ram_size != 0 {
-            panic!("RAM size must be 0 when using MBC2 chip");
+            return Err(CartridgeError::Mbc2RamSize(self.header.ram_size));
         }
+
+        Ok(())
     }
 
-    fn compare_nintendo_logo(&self) -> bool {
-        let nintendo_logo = vec![
-            0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b,
-            0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00, 0x0d,
-            0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e,
-            0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99,
-            0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc,
-            0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e
-        ];
-
-        for i in 0..nintendo_logo.len() {
-            if self.mem_read_byte(0x104 + i as u16) != nintendo_logo[i] {
-                return false;
-            }
-        }
+    fn create_mbc(&self) -> Result<Box<dyn Mbc>, CartridgeError> {
+        let mbc: Box<dyn Mbc> = match self.header.cart_type {
+            CartridgeType::RomOnly
+            | CartridgeType::RomRam
+            | CartridgeType::RomRamBattery => Box::new(RomOnly),
+            CartridgeType::Mbc1
+            | CartridgeType::Mbc1Ram
+            | CartridgeType::Mbc1RamBattery => Box::new(Mbc1::new(self.is_multicart())),
+            CartridgeType::Mbc2
+            | CartridgeType::Mbc2Battery => Box::new(Mbc2::new()),
+            CartridgeType::Mbc3
+            | CartridgeType::Mbc3Ram
+            | CartridgeType::Mbc3RamBattery => Box::new(Mbc3::new(None)),
+            CartridgeType::Mbc3TimerBattery
+            | CartridgeType::Mbc3TimerRamBattery => {
+                Box::new(Mbc3::new(Some(Rtc::new(Arc::clone(&self.clock)))))
+            },
+            CartridgeType::Mbc5
+            | CartridgeType::Mbc5Ram
+            | CartridgeType::Mbc5RamBattery => Box::new(Mbc5::new(false)),
+            CartridgeType::Mbc5Rumble
+            | CartridgeType::Mbc5RumbleRam
+            | CartridgeType::Mbc5RumbleRamBattery => Box::new(Mbc5::new(true)),
+            _ => return Err(CartridgeError::UnsupportedCartridgeType(self.rom[0x147])),
+        };
 
-        true
+        Ok(mbc)
+    }
+
+    // MBC1M multicarts can't be told apart by their header. They are all
+    // 1MiB and have a second game with its own logo starting at bank 0x10
+    fn is_multicart(&self) -> bool {
+        self.rom.len() == MBC1M_ROM_SIZE && self.compare_nintendo_logo(0x10 * ROM_BANK_SIZE)
+    }
+
+    fn compare_nintendo_logo(&self, base: usize) -> bool {
+        self.rom[base + 0x104..base + 0x134] == NINTENDO_LOGO
     }
 
     fn calc_header_checksum(&self) -> u8 {
@@ -239,10 +489,11 @@ impl Cartridge {
     }
 
     fn calc_cart_checksum(&self) -> u16 {
+        // read ROM directly, the checksum covers every bank
         let mut x: u16 = 0;
         for i in 0..self.rom.len() {
             if (i != 0x14e) && (i != 0x14f) {
-                x = x.wrapping_add(self.mem_read_byte(i as u16) as u16);
+                x = x.wrapping_add(self.rom[i] as u16);
             }
         }
 
@@ -252,11 +503,48 @@ impl Cartridge {
 
 impl Memory for Cartridge {
     fn mem_read_byte(&self, addr: u16) -> u8 {
-        self.rom[addr as usize]
+        match addr {
+            // nothing in the slot, the bus floats high
+            0x0000..=0x7fff if self.rom.is_empty() => 0xff,
+            0x0000..=0x7fff => self.rom[self.mbc.rom_offset(addr) % self.rom.len()],
+            0xa000..=0xbfff => {
+                if let Some(val) = self.mbc.read_mapped(addr) {
+                    return val;
+                }
+
+                // unmapped or disabled RAM reads as open bus
+                match self.mbc.ram_offset(addr) {
+                    Some(offset) if !self.ram.is_empty() => self.ram[offset % self.ram.len()],
+                    _ => 0xff,
+                }
+            },
+            _ => panic!("read from invalid address: {:#06x}", addr),
+        }
     }
 
-    fn mem_write_byte(&mut self, _addr: u16, _val: u8) {
-        // do nothing
+    fn mem_write_byte(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x7fff => {
+                self.mbc.write_reg(addr, val);
+                let rumble = self.mbc.rumble();
+                self.set_rumble(rumble);
+            },
+            0xa000..=0xbfff => {
+                if self.mbc.write_mapped(addr, val) {
+                    self.ram_dirty = true;
+                } else if let Some(offset) = self.mbc.ram_offset(addr) {
+                    if !self.ram.is_empty() {
+                        let len = self.ram.len();
+                        self.ram[offset % len] = val;
+                        self.ram_dirty = true;
+                    }
+                }
+            },
+            _ => panic!("write to invalid address: {:#06x}", addr),
+        }
     }
 }
 
+#[cfg(test)]
+mod test;
+
@@ -0,0 +1,492 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use std::convert::TryInto;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::*;
+use super::testing::{self, finish_rom};
+
+// offset within each ROM bank where the bank number is stored
+const BANK_MARKER: u16 = 0x1000;
+
+// Build a ROM image with a valid header where every bank is tagged with
+// its own bank number
+fn build_rom(cart_type: u8, rom_size_code: u8, ram_size_code: u8) -> Vec<u8> {
+    let mut rom = testing::build_rom(cart_type, rom_size_code, ram_size_code);
+
+    for bank in 0..(rom.len() / ROM_BANK_SIZE) {
+        rom[bank * ROM_BANK_SIZE + BANK_MARKER as usize] = bank as u8;
+    }
+
+    finish_rom(&mut rom);
+    rom
+}
+
+// Clock that only moves when the test says so
+struct TestClock(Arc<AtomicU64>);
+
+impl Clock for TestClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Load an MBC3 cart with an RTC driven by the returned clock
+fn load_mbc3_rtc() -> (Cartridge, Arc<AtomicU64>) {
+    let time = Arc::new(AtomicU64::new(1_000_000));
+    let mut cart = Cartridge::new();
+    cart.set_clock(Box::new(TestClock(Arc::clone(&time))));
+    cart.load_rom_data(build_rom(0x10, 0x06, 0x03)).unwrap();
+    cart.mem_write_byte(0x0000, 0x0a);
+    (cart, time)
+}
+
+fn latch(cart: &mut Cartridge) {
+    cart.mem_write_byte(0x6000, 0x00);
+    cart.mem_write_byte(0x6000, 0x01);
+}
+
+fn read_rtc(cart: &mut Cartridge, reg: u8) -> u8 {
+    cart.mem_write_byte(0x4000, reg);
+    cart.mem_read_byte(0xa000)
+}
+
+fn write_rtc(cart: &mut Cartridge, reg: u8, val: u8) {
+    cart.mem_write_byte(0x4000, reg);
+    cart.mem_write_byte(0xa000, val);
+}
+
+fn load(rom: Vec<u8>) -> Cartridge {
+    let mut cart = Cartridge::new();
+    cart.load_rom_data(rom).unwrap();
+    cart
+}
+
+// Verify ROM only carts map 32KiB directly
+#[test]
+fn test_rom_only() {
+    let mut cart = load(build_rom(0x00, 0x00, 0x00));
+
+    assert_eq!(cart.mem_read_byte(BANK_MARKER), 0);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 1);
+
+    cart.mem_write_byte(0x2000, 0x00);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 1);
+    assert_eq!(cart.mem_read_byte(0xa000), 0xff);
+}
+
+// Verify MBC1 ROM bank switching, including upper bits and mode 1
+#[test]
+fn test_mbc1_rom_banking() {
+    // 2MiB, 128 banks
+    let mut cart = load(build_rom(0x01, 0x06, 0x00));
+
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 1);
+
+    cart.mem_write_byte(0x2000, 0x05);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 5);
+
+    // bank 0 is remapped to 1, and only 5 bits are used
+    cart.mem_write_byte(0x2000, 0x00);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 1);
+    cart.mem_write_byte(0x3fff, 0x20);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 1);
+
+    // upper bits from BANK2
+    cart.mem_write_byte(0x2000, 0x03);
+    cart.mem_write_byte(0x4000, 0x02);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 0x43);
+    assert_eq!(cart.mem_read_byte(BANK_MARKER), 0);
+
+    // mode 1 applies BANK2 to 0000..3FFF too
+    cart.mem_write_byte(0x6000, 0x01);
+    assert_eq!(cart.mem_read_byte(BANK_MARKER), 0x40);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 0x43);
+
+    cart.mem_write_byte(0x6000, 0x00);
+    assert_eq!(cart.mem_read_byte(BANK_MARKER), 0);
+}
+
+// Verify bank numbers wrap to the ROM size
+#[test]
+fn test_mbc1_rom_wrap() {
+    // 256KiB, 16 banks
+    let mut cart = load(build_rom(0x01, 0x03, 0x00));
+
+    cart.mem_write_byte(0x2000, 0x12);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 0x02);
+}
+
+// Verify MBC1 RAM enable and RAM banking in mode 1
+#[test]
+fn test_mbc1_ram_banking() {
+    // 32KiB RAM
+    let mut cart = load(build_rom(0x03, 0x00, 0x03));
+
+    cart.mem_write_byte(0xa000, 0x12);
+    assert_eq!(cart.mem_read_byte(0xa000), 0xff);
+
+    cart.mem_write_byte(0x0000, 0x0a);
+    cart.mem_write_byte(0xa000, 0x12);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x12);
+
+    cart.mem_write_byte(0x6000, 0x01);
+    cart.mem_write_byte(0x4000, 0x02);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x00);
+    cart.mem_write_byte(0xa000, 0x34);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x34);
+
+    // mode 0 always uses RAM bank 0
+    cart.mem_write_byte(0x6000, 0x00);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x12);
+
+    cart.mem_write_byte(0x0000, 0x00);
+    assert_eq!(cart.mem_read_byte(0xa000), 0xff);
+}
+
+// Verify MBC1M multicart wiring is detected and used
+#[test]
+fn test_mbc1_multicart() {
+    // 1MiB with a second logo at bank 0x10
+    let mut rom = build_rom(0x01, 0x05, 0x00);
+    let base = 0x10 * ROM_BANK_SIZE;
+    rom[base + 0x104..base + 0x134].copy_from_slice(&NINTENDO_LOGO);
+    finish_rom(&mut rom);
+    let mut cart = load(rom);
+
+    cart.mem_write_byte(0x2000, 0x12);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 0x02);
+
+    cart.mem_write_byte(0x4000, 0x01);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 0x12);
+
+    cart.mem_write_byte(0x6000, 0x01);
+    assert_eq!(cart.mem_read_byte(BANK_MARKER), 0x10);
+}
+
+// Verify battery RAM round trips through a raw .sav file
+#[test]
+fn test_battery_save() {
+    let path = std::env::temp_dir().join(format!("dookieboy_test_{}.sav", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    // 8KiB RAM with battery
+    let mut cart = load(build_rom(0x03, 0x00, 0x02));
+    assert!(cart.has_battery());
+
+    // missing save is fine
+    cart.load_save(&path).unwrap();
+    assert!(!cart.is_ram_dirty());
+
+    cart.mem_write_byte(0x0000, 0x0a);
+    cart.mem_write_byte(0xa000, 0x12);
+    cart.mem_write_byte(0xbfff, 0x34);
+    assert!(cart.is_ram_dirty());
+
+    cart.write_save(&path).unwrap();
+    assert!(!cart.is_ram_dirty());
+    assert_eq!(fs::read(&path).unwrap().len(), 0x2000);
+
+    let mut cart = load(build_rom(0x03, 0x00, 0x02));
+    cart.load_save(&path).unwrap();
+    cart.mem_write_byte(0x0000, 0x0a);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x12);
+    assert_eq!(cart.mem_read_byte(0xbfff), 0x34);
+
+    fs::remove_file(&path).unwrap();
+
+    // no battery, nothing to save
+    let cart = load(build_rom(0x02, 0x00, 0x02));
+    assert!(!cart.has_battery());
+}
+
+// Verify MBC3 ROM and RAM bank switching
+#[test]
+fn test_mbc3_banking() {
+    // 2MiB ROM, 32KiB RAM
+    let mut cart = load(build_rom(0x13, 0x06, 0x03));
+
+    cart.mem_write_byte(0x2000, 0x00);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 1);
+    cart.mem_write_byte(0x2000, 0x7f);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 0x7f);
+    assert_eq!(cart.mem_read_byte(BANK_MARKER), 0);
+
+    cart.mem_write_byte(0x0000, 0x0a);
+    cart.mem_write_byte(0x4000, 0x03);
+    cart.mem_write_byte(0xa000, 0x33);
+    cart.mem_write_byte(0x4000, 0x01);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x00);
+    cart.mem_write_byte(0x4000, 0x03);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x33);
+
+    // no RTC on this cart, so RTC selects read open bus
+    cart.mem_write_byte(0x4000, 0x08);
+    assert_eq!(cart.mem_read_byte(0xa000), 0xff);
+}
+
+// Verify RTC registers only change when latched
+#[test]
+fn test_mbc3_rtc_latch() {
+    let (mut cart, time) = load_mbc3_rtc();
+
+    time.fetch_add(3661, Ordering::Relaxed);
+    assert_eq!(read_rtc(&mut cart, 0x08), 0);
+
+    latch(&mut cart);
+    assert_eq!(read_rtc(&mut cart, 0x08), 1);
+    assert_eq!(read_rtc(&mut cart, 0x09), 1);
+    assert_eq!(read_rtc(&mut cart, 0x0a), 1);
+
+    // latching needs 0x00 then 0x01
+    time.fetch_add(1, Ordering::Relaxed);
+    cart.mem_write_byte(0x6000, 0x01);
+    assert_eq!(read_rtc(&mut cart, 0x08), 1);
+    latch(&mut cart);
+    assert_eq!(read_rtc(&mut cart, 0x08), 2);
+}
+
+// Verify the day counter, carry and halt bits
+#[test]
+fn test_mbc3_rtc_days() {
+    let (mut cart, time) = load_mbc3_rtc();
+
+    write_rtc(&mut cart, 0x0b, 0xff);
+    write_rtc(&mut cart, 0x0c, 0x01);
+    time.fetch_add(86400, Ordering::Relaxed);
+    latch(&mut cart);
+    assert_eq!(read_rtc(&mut cart, 0x0b), 0x00);
+    assert_eq!(read_rtc(&mut cart, 0x0c), 0x80);
+
+    // halted clock doesn't move
+    write_rtc(&mut cart, 0x0c, 0x40);
+    time.fetch_add(1000, Ordering::Relaxed);
+    latch(&mut cart);
+    assert_eq!(read_rtc(&mut cart, 0x08), 0);
+    assert_eq!(read_rtc(&mut cart, 0x0c), 0x40);
+
+    // resumes from where it was halted
+    write_rtc(&mut cart, 0x0c, 0x00);
+    time.fetch_add(10, Ordering::Relaxed);
+    latch(&mut cart);
+    assert_eq!(read_rtc(&mut cart, 0x08), 10);
+}
+
+// Verify out of range values wrap at the register width without carrying
+#[test]
+fn test_mbc3_rtc_invalid_values() {
+    let (mut cart, time) = load_mbc3_rtc();
+
+    write_rtc(&mut cart, 0x08, 62);
+    time.fetch_add(3, Ordering::Relaxed);
+    latch(&mut cart);
+    assert_eq!(read_rtc(&mut cart, 0x08), 1);
+    assert_eq!(read_rtc(&mut cart, 0x09), 0);
+}
+
+// Verify RTC state is saved after RAM and keeps running while closed
+#[test]
+fn test_mbc3_rtc_save() {
+    let path = std::env::temp_dir().join(format!("dookieboy_rtc_test_{}.sav", std::process::id()));
+
+    let (mut cart, time) = load_mbc3_rtc();
+    cart.mem_write_byte(0x4000, 0x00);
+    cart.mem_write_byte(0xa000, 0x12);
+    write_rtc(&mut cart, 0x09, 5);
+    cart.write_save(&path).unwrap();
+
+    let data = fs::read(&path).unwrap();
+    assert_eq!(data.len(), 0x8000 + 48);
+    assert_eq!(data[0x8000 + 4], 5);
+    assert_eq!(u64::from_le_bytes(data[0x8000 + 40..].try_into().unwrap()), time.load(Ordering::Relaxed));
+
+    let (mut cart, time2) = load_mbc3_rtc();
+    time2.store(time.load(Ordering::Relaxed) + 120, Ordering::Relaxed);
+    cart.load_save(&path).unwrap();
+    latch(&mut cart);
+    assert_eq!(read_rtc(&mut cart, 0x09), 7);
+    cart.mem_write_byte(0x4000, 0x00);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x12);
+
+    fs::remove_file(&path).unwrap();
+}
+
+// Verify MBC5 9 bit ROM banks and 16 RAM banks
+#[test]
+fn test_mbc5_banking() {
+    // 8MiB ROM, 128KiB RAM
+    let mut rom = build_rom(0x1b, 0x08, 0x04);
+    rom[0x1ff * ROM_BANK_SIZE + BANK_MARKER as usize + 1] = 0x01;
+    finish_rom(&mut rom);
+    let mut cart = load(rom);
+
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 1);
+
+    // bank 0 is allowed in 4000..7FFF
+    cart.mem_write_byte(0x2000, 0x00);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 0);
+
+    cart.mem_write_byte(0x2000, 0xff);
+    cart.mem_write_byte(0x3000, 0x01);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 0xff);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER + 1), 0x01);
+
+    cart.mem_write_byte(0x0000, 0x0a);
+    cart.mem_write_byte(0x4000, 0x0f);
+    cart.mem_write_byte(0xa000, 0x5a);
+    cart.mem_write_byte(0x4000, 0x00);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x00);
+    cart.mem_write_byte(0x4000, 0x0f);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x5a);
+}
+
+// Verify the rumble motor bit is reported through the handler
+#[test]
+fn test_mbc5_rumble() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let handler_events = Arc::clone(&events);
+
+    let mut cart = Cartridge::new();
+    cart.set_rumble_handler(Box::new(move |on| handler_events.lock().unwrap().push(on)));
+    cart.load_rom_data(build_rom(0x1e, 0x02, 0x03)).unwrap();
+
+    cart.mem_write_byte(0x0000, 0x0a);
+    cart.mem_write_byte(0x4000, 0x0b);
+    cart.mem_write_byte(0x4000, 0x09);
+    cart.mem_write_byte(0x4000, 0x03);
+
+    assert_eq!(*events.lock().unwrap(), vec![true, false]);
+
+    // motor bit is not part of the RAM bank
+    cart.mem_write_byte(0xa000, 0x77);
+    cart.mem_write_byte(0x4000, 0x0b);
+    assert_eq!(cart.mem_read_byte(0xa000), 0x77);
+    assert_eq!(*events.lock().unwrap(), vec![true, false, true]);
+}
+
+// Verify MBC2 register select, ROM banks and the built-in nibble RAM
+#[test]
+fn test_mbc2() {
+    // 256KiB, 16 banks
+    let mut cart = load(build_rom(0x06, 0x03, 0x00));
+    assert!(cart.has_battery());
+
+    // bit 8 set selects the ROM bank
+    cart.mem_write_byte(0x2100, 0x0f);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 0x0f);
+    cart.mem_write_byte(0x0100, 0x00);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 1);
+
+    // bit 8 clear selects RAM enable, no matter which half it is in
+    cart.mem_write_byte(0xa000, 0x05);
+    assert_eq!(cart.mem_read_byte(0xa000), 0xff);
+    cart.mem_write_byte(0x2000, 0x0a);
+    assert_eq!(cart.mem_read_byte(0x4000 + BANK_MARKER), 1);
+
+    cart.mem_write_byte(0xa000, 0xa5);
+    assert_eq!(cart.mem_read_byte(0xa000), 0xf5);
+    assert_eq!(cart.mem_read_byte(0xa200), 0xf5);
+    assert_eq!(cart.mem_read_byte(0xbe00), 0xf5);
+
+    cart.mem_write_byte(0xa3ff, 0x0c);
+    assert_eq!(cart.mem_read_byte(0xa1ff), 0xfc);
+
+    // built-in RAM is the whole save
+    let path = std::env::temp_dir().join(format!("dookieboy_mbc2_test_{}.sav", std::process::id()));
+    cart.write_save(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap().len(), 512);
+
+    let mut cart = load(build_rom(0x06, 0x03, 0x00));
+    cart.load_save(&path).unwrap();
+    cart.mem_write_byte(0x0000, 0x0a);
+    assert_eq!(cart.mem_read_byte(0xa000), 0xf5);
+    assert_eq!(cart.mem_read_byte(0xa1ff), 0xfc);
+
+    fs::remove_file(&path).unwrap();
+}
+
+// Verify broken headers are rejected with the right error
+#[test]
+fn test_header_errors() {
+    let mut cart = Cartridge::new();
+
+    assert!(matches!(cart.load_rom_data(vec![0; 0x100]), Err(CartridgeError::MissingHeader)));
+    assert!(!cart.is_loaded());
+
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[0x14d] ^= 0xff;
+    assert!(matches!(cart.load_rom_data(rom), Err(CartridgeError::HeaderChecksum { .. })));
+
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[0x147] = 0x04;
+    finish_rom(&mut rom);
+    assert!(matches!(cart.load_rom_data(rom), Err(CartridgeError::InvalidCartridgeType(0x04))));
+
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[0x147] = 0x20;
+    finish_rom(&mut rom);
+    assert!(matches!(cart.load_rom_data(rom), Err(CartridgeError::UnsupportedCartridgeType(0x20))));
+
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[0x148] = 0x52;
+    finish_rom(&mut rom);
+    assert!(matches!(cart.load_rom_data(rom), Err(CartridgeError::InvalidRomSize(0x52))));
+
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[0x149] = 0x01;
+    finish_rom(&mut rom);
+    assert!(matches!(cart.load_rom_data(rom), Err(CartridgeError::InvalidRamSize(0x01))));
+
+    let rom = build_rom(0x05, 0x00, 0x02);
+    assert!(matches!(cart.load_rom_data(rom), Err(CartridgeError::Mbc2RamSize(8192))));
+
+    assert!(cart.load_rom_data(build_rom(0x00, 0x00, 0x00)).is_ok());
+    assert!(cart.is_loaded());
+}
+
+// Verify lenient mode only lets global checksum and logo mismatches through
+#[test]
+fn test_lenient() {
+    let mut bad_checksum = build_rom(0x00, 0x00, 0x00);
+    bad_checksum[0x14f] ^= 0xff;
+
+    let mut bad_logo = build_rom(0x00, 0x00, 0x00);
+    bad_logo[0x104] = 0;
+    finish_rom(&mut bad_logo);
+
+    let mut bad_header = build_rom(0x00, 0x00, 0x00);
+    bad_header[0x14d] ^= 0xff;
+
+    let mut cart = Cartridge::new();
+    assert!(matches!(cart.load_rom_data(bad_checksum.clone()), Err(CartridgeError::GlobalChecksum { .. })));
+    assert!(matches!(cart.load_rom_data(bad_logo.clone()), Err(CartridgeError::InvalidLogo)));
+
+    assert!(cart.warnings().is_empty());
+
+    cart.set_lenient(true);
+    assert!(cart.load_rom_data(bad_checksum).is_ok());
+    assert!(matches!(cart.warnings(), [CartridgeError::GlobalChecksum { .. }]));
+    assert!(cart.load_rom_data(bad_logo).is_ok());
+    assert!(matches!(cart.warnings(), [CartridgeError::InvalidLogo]));
+    assert!(cart.load_rom_data(bad_header).is_err());
+}
+
+// Verify an empty slot reads as open bus
+#[test]
+fn test_not_loaded() {
+    let cart = Cartridge::new();
+    assert_eq!(cart.mem_read_byte(0x0000), 0xff);
+    assert_eq!(cart.mem_read_byte(0x7fff), 0xff);
+}
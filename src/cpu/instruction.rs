@@ -27,8 +27,10 @@ pub enum Instruction {
     Noop(),
     Inc(Register8Bit),
     Inc16(Register16Bit),
+    IncMem(), // always uses HL
     Dec(Register8Bit),
     Dec16(Register16Bit),
+    DecMem(), // always uses HL
     And(Register8Bit),
     AndFromMem(), // always uses HL
     AndImm(),
@@ -84,11 +86,13 @@ pub enum Instruction {
     Push(Register16Bit),
     Pop(Register16Bit),
     Ret(BranchCondition),
+    Reti(),
     Rst(RstVec),
     Call(BranchCondition),
     ToggleCarryFlag(),
     SetCarryFlag(),
     Invert(),
+    Daa(),
     Stop(),
     Halt(),
     Ei(),
@@ -114,6 +118,8 @@ impl Instruction {
             0x13 => Some(Instruction::Inc16(Register16Bit::DE)),
             0x23 => Some(Instruction::Inc16(Register16Bit::HL)),
             0x33 => Some(Instruction::Inc16(Register16Bit::SP)),
+            // INC (HL)
+            0x34 => Some(Instruction::IncMem()),
             // DEC r
             0x05 => Some(Instruction::Dec(Register8Bit::B)),
             0x0d => Some(Instruction::Dec(Register8Bit::C)),
@@ -127,6 +133,8 @@ impl Instruction {
             0x1b => Some(Instruction::Dec16(Register16Bit::DE)),
             0x2b => Some(Instruction::Dec16(Register16Bit::HL)),
             0x3b => Some(Instruction::Dec16(Register16Bit::SP)),
+            // DEC (HL)
+            0x35 => Some(Instruction::DecMem()),
             // AND r
             0xa0 => Some(Instruction::And(Register8Bit::B)),
             0xa1 => Some(Instruction::And(Register8Bit::C)),
@@ -383,6 +391,8 @@ impl Instruction {
             0xc8 => Some(Instruction::Ret(BranchCondition::Z)),
             0xd8 => Some(Instruction::Ret(BranchCondition::C)),
             0xc9 => Some(Instruction::Ret(BranchCondition::NONE)),
+            // RETI
+            0xd9 => Some(Instruction::Reti()),
             // RST
             0xc7 => Some(Instruction::Rst(RstVec::ZERO)),
             0xcf => Some(Instruction::Rst(RstVec::ONE)),
@@ -404,6 +414,8 @@ impl Instruction {
             0x37 => Some(Instruction::SetCarryFlag()),
             // CPL
             0x2f => Some(Instruction::Invert()),
+            // DAA
+            0x27 => Some(Instruction::Daa()),
             // STOP
             0x10 => Some(Instruction::Stop()),
             // HALT
@@ -436,6 +448,8 @@ impl Instruction {
             Instruction::Inc16(Register16Bit::DE) => 0x13,
             Instruction::Inc16(Register16Bit::HL) => 0x23,
             Instruction::Inc16(Register16Bit::SP) => 0x33,
+            // INC (HL)
+            Instruction::IncMem() => 0x34,
             // DEC r
             Instruction::Dec(Register8Bit::B) => 0x05,
             Instruction::Dec(Register8Bit::C) => 0x0d,
@@ -449,6 +463,8 @@ impl Instruction {
             Instruction::Dec16(Register16Bit::DE) => 0x1b,
             Instruction::Dec16(Register16Bit::HL) => 0x2b,
             Instruction::Dec16(Register16Bit::SP) => 0x3b,
+            // DEC (HL)
+            Instruction::DecMem() => 0x35,
             // AND r
             Instruction::And(Register8Bit::B) => 0xa0,
             Instruction::And(Register8Bit::C) => 0xa1,
@@ -705,6 +721,8 @@ impl Instruction {
             Instruction::Ret(BranchCondition::Z) => 0xc8,
             Instruction::Ret(BranchCondition::C) => 0xd8,
             Instruction::Ret(BranchCondition::NONE) => 0xc9,
+            // RETI
+            Instruction::Reti() => 0xd9,
             // RST
             Instruction::Rst(RstVec::ZERO) => 0xc7,
             Instruction::Rst(RstVec::ONE) => 0xcf,
@@ -726,6 +744,8 @@ impl Instruction {
             Instruction::SetCarryFlag() => 0x37,
             // CPL
             Instruction::Invert() => 0x2f,
+            // DAA
+            Instruction::Daa() => 0x27,
             // STOP
             Instruction::Stop() => 0x10,
             // HALT
@@ -820,61 +840,61 @@ impl CbInstruction {
             0x45 => Some(CbInstruction::Bit(Register8Bit::L, 0)),
             0x47 => Some(CbInstruction::Bit(Register8Bit::A, 0)),
             // 1 bit
-            0x48 => Some(CbInstruction::Bit(Register8Bit::B, 0)),
-            0x49 => Some(CbInstruction::Bit(Register8Bit::C, 0)),
-            0x4a => Some(CbInstruction::Bit(Register8Bit::D, 0)),
-            0x4b => Some(CbInstruction::Bit(Register8Bit::E, 0)),
-            0x4c => Some(CbInstruction::Bit(Register8Bit::H, 0)),
-            0x4d => Some(CbInstruction::Bit(Register8Bit::L, 0)),
-            0x4f => Some(CbInstruction::Bit(Register8Bit::A, 0)),
+            0x48 => Some(CbInstruction::Bit(Register8Bit::B, 1)),
+            0x49 => Some(CbInstruction::Bit(Register8Bit::C, 1)),
+            0x4a => Some(CbInstruction::Bit(Register8Bit::D, 1)),
+            0x4b => Some(CbInstruction::Bit(Register8Bit::E, 1)),
+            0x4c => Some(CbInstruction::Bit(Register8Bit::H, 1)),
+            0x4d => Some(CbInstruction::Bit(Register8Bit::L, 1)),
+            0x4f => Some(CbInstruction::Bit(Register8Bit::A, 1)),
             // 2 bit
-            0x50 => Some(CbInstruction::Bit(Register8Bit::B, 0)),
-            0x51 => Some(CbInstruction::Bit(Register8Bit::C, 0)),
-            0x52 => Some(CbInstruction::Bit(Register8Bit::D, 0)),
-            0x53 => Some(CbInstruction::Bit(Register8Bit::E, 0)),
-            0x54 => Some(CbInstruction::Bit(Register8Bit::H, 0)),
-            0x55 => Some(CbInstruction::Bit(Register8Bit::L, 0)),
-            0x57 => Some(CbInstruction::Bit(Register8Bit::A, 0)),
+            0x50 => Some(CbInstruction::Bit(Register8Bit::B, 2)),
+            0x51 => Some(CbInstruction::Bit(Register8Bit::C, 2)),
+            0x52 => Some(CbInstruction::Bit(Register8Bit::D, 2)),
+            0x53 => Some(CbInstruction::Bit(Register8Bit::E, 2)),
+            0x54 => Some(CbInstruction::Bit(Register8Bit::H, 2)),
+            0x55 => Some(CbInstruction::Bit(Register8Bit::L, 2)),
+            0x57 => Some(CbInstruction::Bit(Register8Bit::A, 2)),
             // 3 bit
-            0x58 => Some(CbInstruction::Bit(Register8Bit::B, 0)),
-            0x59 => Some(CbInstruction::Bit(Register8Bit::C, 0)),
-            0x5a => Some(CbInstruction::Bit(Register8Bit::D, 0)),
-            0x5b => Some(CbInstruction::Bit(Register8Bit::E, 0)),
-            0x5c => Some(CbInstruction::Bit(Register8Bit::H, 0)),
-            0x5d => Some(CbInstruction::Bit(Register8Bit::L, 0)),
-            0x5f => Some(CbInstruction::Bit(Register8Bit::A, 0)),
+            0x58 => Some(CbInstruction::Bit(Register8Bit::B, 3)),
+            0x59 => Some(CbInstruction::Bit(Register8Bit::C, 3)),
+            0x5a => Some(CbInstruction::Bit(Register8Bit::D, 3)),
+            0x5b => Some(CbInstruction::Bit(Register8Bit::E, 3)),
+            0x5c => Some(CbInstruction::Bit(Register8Bit::H, 3)),
+            0x5d => Some(CbInstruction::Bit(Register8Bit::L, 3)),
+            0x5f => Some(CbInstruction::Bit(Register8Bit::A, 3)),
             // 4 bit
-            0x60 => Some(CbInstruction::Bit(Register8Bit::B, 0)),
-            0x61 => Some(CbInstruction::Bit(Register8Bit::C, 0)),
-            0x62 => Some(CbInstruction::Bit(Register8Bit::D, 0)),
-            0x63 => Some(CbInstruction::Bit(Register8Bit::E, 0)),
-            0x64 => Some(CbInstruction::Bit(Register8Bit::H, 0)),
-            0x65 => Some(CbInstruction::Bit(Register8Bit::L, 0)),
-            0x67 => Some(CbInstruction::Bit(Register8Bit::A, 0)),
+            0x60 => Some(CbInstruction::Bit(Register8Bit::B, 4)),
+            0x61 => Some(CbInstruction::Bit(Register8Bit::C, 4)),
+            0x62 => Some(CbInstruction::Bit(Register8Bit::D, 4)),
+            0x63 => Some(CbInstruction::Bit(Register8Bit::E, 4)),
+            0x64 => Some(CbInstruction::Bit(Register8Bit::H, 4)),
+            0x65 => Some(CbInstruction::Bit(Register8Bit::L, 4)),
+            0x67 => Some(CbInstruction::Bit(Register8Bit::A, 4)),
             // 5 bit
-            0x68 => Some(CbInstruction::Bit(Register8Bit::B, 0)),
-            0x69 => Some(CbInstruction::Bit(Register8Bit::C, 0)),
-            0x6a => Some(CbInstruction::Bit(Register8Bit::D, 0)),
-            0x6b => Some(CbInstruction::Bit(Register8Bit::E, 0)),
-            0x6c => Some(CbInstruction::Bit(Register8Bit::H, 0)),
-            0x6d => Some(CbInstruction::Bit(Register8Bit::L, 0)),
-            0x6f => Some(CbInstruction::Bit(Register8Bit::A, 0)),
+            0x68 => Some(CbInstruction::Bit(Register8Bit::B, 5)),
+            0x69 => Some(CbInstruction::Bit(Register8Bit::C, 5)),
+            0x6a => Some(CbInstruction::Bit(Register8Bit::D, 5)),
+            0x6b => Some(CbInstruction::Bit(Register8Bit::E, 5)),
+            0x6c => Some(CbInstruction::Bit(Register8Bit::H, 5)),
+            0x6d => Some(CbInstruction::Bit(Register8Bit::L, 5)),
+            0x6f => Some(CbInstruction::Bit(Register8Bit::A, 5)),
             // 6 bit
-            0x70 => Some(CbInstruction::Bit(Register8Bit::B, 0)),
-            0x71 => Some(CbInstruction::Bit(Register8Bit::C, 0)),
-            0x72 => Some(CbInstruction::Bit(Register8Bit::D, 0)),
-            0x73 => Some(CbInstruction::Bit(Register8Bit::E, 0)),
-            0x74 => Some(CbInstruction::Bit(Register8Bit::H, 0)),
-            0x75 => Some(CbInstruction::Bit(Register8Bit::L, 0)),
-            0x77 => Some(CbInstruction::Bit(Register8Bit::A, 0)),
+            0x70 => Some(CbInstruction::Bit(Register8Bit::B, 6)),
+            0x71 => Some(CbInstruction::Bit(Register8Bit::C, 6)),
+            0x72 => Some(CbInstruction::Bit(Register8Bit::D, 6)),
+            0x73 => Some(CbInstruction::Bit(Register8Bit::E, 6)),
+            0x74 => Some(CbInstruction::Bit(Register8Bit::H, 6)),
+            0x75 => Some(CbInstruction::Bit(Register8Bit::L, 6)),
+            0x77 => Some(CbInstruction::Bit(Register8Bit::A, 6)),
             // 7 bit
-            0x78 => Some(CbInstruction::Bit(Register8Bit::B, 0)),
-            0x79 => Some(CbInstruction::Bit(Register8Bit::C, 0)),
-            0x7a => Some(CbInstruction::Bit(Register8Bit::D, 0)),
-            0x7b => Some(CbInstruction::Bit(Register8Bit::E, 0)),
-            0x7c => Some(CbInstruction::Bit(Register8Bit::H, 0)),
-            0x7d => Some(CbInstruction::Bit(Register8Bit::L, 0)),
-            0x7f => Some(CbInstruction::Bit(Register8Bit::A, 0)),
+            0x78 => Some(CbInstruction::Bit(Register8Bit::B, 7)),
+            0x79 => Some(CbInstruction::Bit(Register8Bit::C, 7)),
+            0x7a => Some(CbInstruction::Bit(Register8Bit::D, 7)),
+            0x7b => Some(CbInstruction::Bit(Register8Bit::E, 7)),
+            0x7c => Some(CbInstruction::Bit(Register8Bit::H, 7)),
+            0x7d => Some(CbInstruction::Bit(Register8Bit::L, 7)),
+            0x7f => Some(CbInstruction::Bit(Register8Bit::A, 7)),
             // BIT b,(HL)
             0x46 => Some(CbInstruction::BitMem(0)),
             0x4e => Some(CbInstruction::BitMem(1)),
@@ -14,13 +14,12 @@
 mod instruction;
 
 use std::fmt;
-use std::rc::Rc;
-use std::cell::RefCell;
 
-use crate::memory::Memory;
+use crate::bus::Bus;
+#[cfg(test)]
+use crate::bus::FlatRam;
 use crate::cpu::instruction::{ BranchCondition, Instruction, CbInstruction };
 use crate::intc::Interrupt;
-use crate::mmu::Mmu;
 
 #[derive(Debug, Copy, Clone)]
 pub enum Register8Bit {
@@ -58,126 +57,82 @@ pub enum RstVec {
 
 const NUM_GP_REGS: usize = 10;
 
-#[cfg(test)]
-const TEST_RAM_SIZE: usize = 0xffff;
-
-#[cfg(test)]
-struct TestRam {
-    ram: [u8; TEST_RAM_SIZE],
-}
-
-#[cfg(test)]
-impl TestRam {
-    fn new() -> TestRam {
-        TestRam {
-            ram: [0; TEST_RAM_SIZE],
-        }
-    }
-
-    fn load_from_slice(&mut self, data: &[u8]) {
-        self.ram[..data.len()].clone_from_slice(data);
-    }
-}
-
-#[cfg(test)]
-impl Memory for TestRam {
-    fn mem_read_byte(&self, addr: u16) -> u8 {
-        if addr > TEST_RAM_SIZE as u16{
-            panic!("Address {:#06x} outside of test rom size {}", addr, TEST_RAM_SIZE);
-        }
-
-        self.ram[addr as usize]
-    }
-
-    fn mem_write_byte(&mut self, addr: u16, val: u8) {
-        if addr > TEST_RAM_SIZE as u16{
-            panic!("Address {:#06x} outside of test rom size {}", addr, TEST_RAM_SIZE);
-        }
-
-        self.ram[addr as usize] = val;
-    }
-}
+const IF_ADDR: u16 = 0xff0f;
+const IE_ADDR: u16 = 0xffff;
 
-pub struct Cpu {
+pub struct Cpu<B: Bus> {
     rf: [u8; NUM_GP_REGS],
     pc: u16,
-    mmu: Rc<RefCell<Mmu>>,
+    bus: B,
     cycles: usize,
     stopped: bool,
     halted: bool,
-
-    #[cfg(test)]
-    test_ram: TestRam,
+    halt_bug: bool,
+    ei_pending: bool,
+    ei_taking_effect: bool,
+    locked: bool,
+    breakpoint_enabled: bool,
+    breakpoint_hit: bool,
 }
 
-impl fmt::Display for Cpu {
+impl<B: Bus> fmt::Display for Cpu<B> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.dump_to_string())
     }
 }
 
-impl Cpu {
-    pub fn new(mmu: Rc<RefCell<Mmu>>) -> Cpu {
+impl<B: Bus> Cpu<B> {
+    pub fn new(bus: B) -> Cpu<B> {
         Cpu {
             rf: [0; NUM_GP_REGS],
             pc: 0,
-            mmu: mmu,
+            bus,
             cycles: 0,
 
             stopped: false,
             halted: false,
-
-            #[cfg(test)]
-            test_ram: TestRam::new(),
+            halt_bug: false,
+            ei_pending: false,
+            ei_taking_effect: false,
+            locked: false,
+            breakpoint_enabled: false,
+            breakpoint_hit: false,
         }
     }
 
-    #[cfg(test)]
-    fn load_test_ram(&mut self, data: &[u8]) {
-        self.test_ram.load_from_slice(data);
+    pub fn bus(&self) -> &B {
+        &self.bus
     }
 
-    #[cfg(test)]
-    fn read_byte(&self, addr: u16) -> u8 {
-        self.test_ram.mem_read_byte(addr)
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
     }
 
-    #[cfg(test)]
-    fn write_byte(&mut self, addr: u16, val: u8) {
-        self.test_ram.mem_write_byte(addr, val);
+    fn ime(&self) -> bool {
+        self.bus.ime()
     }
 
-    #[cfg(test)]
-    fn read_word(&self, addr: u16) -> u16 {
-        self.test_ram.mem_read_word_le(addr)
-    }
-
-    #[cfg(test)]
-    fn write_word(&mut self, addr: u16, val: u16) {
-        self.test_ram.mem_write_word_le(addr, val);
+    fn set_ime(&mut self, ime: bool) {
+        self.bus.set_ime(ime);
     }
 
-    #[cfg(not(test))]
     fn read_byte(&self, addr: u16) -> u8 {
-        self.mmu.borrow().mem_read_byte(addr)
+        self.bus.mem_read_byte(addr)
     }
 
-    #[cfg(not(test))]
     fn write_byte(&mut self, addr: u16, val: u8) {
-        self.mmu.borrow_mut().mem_write_byte(addr, val);
+        self.bus.mem_write_byte(addr, val);
     }
 
-    #[cfg(not(test))]
     fn read_word(&self, addr: u16) -> u16 {
-        self.mmu.borrow_mut().mem_read_word_le(addr)
+        self.bus.mem_read_word_le(addr)
     }
 
-    #[cfg(not(test))]
     fn write_word(&mut self, addr: u16, val: u16) {
-        self.mmu.borrow_mut().mem_write_word_le(addr, val);
+        self.bus.mem_write_word_le(addr, val);
     }
 
-    fn get_reg(&self, regop: Register8Bit) -> u8 {
+    pub fn get_reg(&self, regop: Register8Bit) -> u8 {
         self.rf[regop as usize]
     }
 
@@ -477,20 +432,104 @@ impl Cpu {
         self.set_flag(Flag::C, did_wrap);
     }
 
+    // H and C come from adding the offset to the low byte of SP as if it
+    // were unsigned, the same as ADD A
     fn add_sp_s8(&mut self, operand: i8) {
         let sp = self.get_reg_16(Register16Bit::SP);
         let op = (operand as i16) as u16;
-        let (result, did_wrap) = sp.overflowing_add(op);
+        let result = sp.wrapping_add(op);
 
         self.set_flag(Flag::Z, false);
         self.set_flag(Flag::N, false);
 
-        let h = (sp & 0xf).wrapping_add((op as u16) & 0xf) > 0xf;
+        let h = (sp & 0xf) + (op & 0xf) > 0xf;
+        let c = (sp & 0xff) + (op & 0xff) > 0xff;
         self.set_flag(Flag::H, h);
-        self.set_flag(Flag::C, did_wrap);
+        self.set_flag(Flag::C, c);
         self.set_reg_16(Register16Bit::SP, result);
     }
 
+    fn inc(&mut self, regop: Register8Bit) {
+        let val = self.get_reg(regop);
+        let result = val.wrapping_add(1);
+        self.set_reg(regop, result);
+
+        self.set_flag(Flag::Z, result == 0);
+        self.set_flag(Flag::N, false);
+        self.set_flag(Flag::H, (val & 0xf) == 0xf);
+        // do not set carry flag
+    }
+
+    fn dec(&mut self, regop: Register8Bit) {
+        let val = self.get_reg(regop);
+        let result = val.wrapping_sub(1);
+        self.set_reg(regop, result);
+
+        self.set_flag(Flag::Z, result == 0);
+        self.set_flag(Flag::N, true);
+        self.set_flag(Flag::H, (val & 0xf) == 0);
+        // do not set carry flag
+    }
+
+    fn inc_mem(&mut self) {
+        let addr = self.get_reg_16(Register16Bit::HL);
+        let val = self.read_byte(addr);
+        let result = val.wrapping_add(1);
+        self.write_byte(addr, result);
+
+        self.set_flag(Flag::Z, result == 0);
+        self.set_flag(Flag::N, false);
+        self.set_flag(Flag::H, (val & 0xf) == 0xf);
+        // do not set carry flag
+    }
+
+    fn dec_mem(&mut self) {
+        let addr = self.get_reg_16(Register16Bit::HL);
+        let val = self.read_byte(addr);
+        let result = val.wrapping_sub(1);
+        self.write_byte(addr, result);
+
+        self.set_flag(Flag::Z, result == 0);
+        self.set_flag(Flag::N, true);
+        self.set_flag(Flag::H, (val & 0xf) == 0);
+        // do not set carry flag
+    }
+
+    /*
+     * Adjust A back into BCD after an addition or subtraction of two BCD
+     * numbers. N tells us which one it was, H and C tell us which digits
+     * overflowed.
+     */
+    fn daa(&mut self) {
+        let mut a = self.get_reg(Register8Bit::A);
+        let mut adjust = 0;
+        let mut carry = self.get_flag(Flag::C);
+
+        if !self.get_flag(Flag::N) {
+            if carry || a > 0x99 {
+                adjust |= 0x60;
+                carry = true;
+            }
+            if self.get_flag(Flag::H) || (a & 0xf) > 0x9 {
+                adjust |= 0x06;
+            }
+            a = a.wrapping_add(adjust);
+        } else {
+            if carry {
+                adjust |= 0x60;
+            }
+            if self.get_flag(Flag::H) {
+                adjust |= 0x06;
+            }
+            a = a.wrapping_sub(adjust);
+        }
+
+        self.set_reg(Register8Bit::A, a);
+        self.set_flag(Flag::Z, a == 0);
+        self.set_flag(Flag::H, false);
+        self.set_flag(Flag::C, carry);
+    }
+
     fn inc_16(&mut self, pair: Register16Bit) {
         let r = self.get_reg_16(pair);
         self.set_reg_16(pair, r.wrapping_add(1));
@@ -515,10 +554,11 @@ impl Cpu {
 
     // TODO consolidate ALU operations
     fn ld_sp_offset_to_hl(&mut self, pc: u16) {
-        let operand = self.read_byte(pc + 1) as i8;
+        let operand = self.read_byte(pc.wrapping_add(1)) as i8;
         let sp = self.get_reg_16(Register16Bit::SP);
-        let (result, did_carry) = sp.overflowing_add(operand as u16);
+        let result = sp.wrapping_add(operand as u16);
         let did_half_carry = ((sp & 0xf) + (operand as u16 & 0xf)) & 0x10 == 0x10;
+        let did_carry = ((sp & 0xff) + (operand as u16 & 0xff)) & 0x100 == 0x100;
         self.set_reg_16(Register16Bit::HL, result);
         self.set_flag(Flag::C, did_carry);
         self.set_flag(Flag::H, did_half_carry);
@@ -528,18 +568,21 @@ impl Cpu {
 
     fn ld_sp_to_imm_mem(&mut self, pc: u16) {
         let sp = self.get_reg_16(Register16Bit::SP);
-        let addr = self.read_word(pc + 1);
+        let addr = self.read_word(pc.wrapping_add(1));
         self.write_word(addr, sp);
     }
 
+    // the high byte is written first, same as dispatch_interrupt
     fn push(&mut self, val: u16) {
-        self.set_sp(self.get_sp() - 2);
-        self.write_word(self.get_sp(), val);
+        let sp = self.get_sp();
+        self.write_byte(sp.wrapping_sub(1), (val >> 8) as u8);
+        self.write_byte(sp.wrapping_sub(2), (val & 0xff) as u8);
+        self.set_sp(sp.wrapping_sub(2));
     }
 
     fn pop(&mut self) -> u16 {
         let val = self.read_word(self.get_sp());
-        self.set_sp(self.get_sp() + 2);
+        self.set_sp(self.get_sp().wrapping_add(2));
         val
     }
 
@@ -656,321 +699,359 @@ impl Cpu {
 
         match instruction {
             Instruction::Noop() => {
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Inc(regop) => {
-                self.add(regop, 1, false);
-                (pc + 1, 1)
+                self.inc(regop);
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Inc16(pair) => {
                 self.inc_16(pair);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
+            },
+            Instruction::IncMem() => {
+                self.inc_mem();
+                (pc.wrapping_add(1), 3)
             },
             Instruction::Dec(regop) => {
-                self.subtract(regop, 1, false);
-                (pc + 1, 1)
+                self.dec(regop);
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Dec16(pair) => {
                 self.dec_16(pair);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
+            },
+            Instruction::DecMem() => {
+                self.dec_mem();
+                (pc.wrapping_add(1), 3)
             },
             Instruction::And(regop) => {
                 self.and(Register8Bit::A, self.get_reg(regop));
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::AndFromMem() => {
                 self.and(Register8Bit::A, self.read_byte(self.get_reg_16(Register16Bit::HL)));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::AndImm() => {
-                self.and(Register8Bit::A, self.read_byte(pc + 1));
-                (pc + 2, 2)
+                self.and(Register8Bit::A, self.read_byte(pc.wrapping_add(1)));
+                (pc.wrapping_add(2), 2)
             },
             Instruction::Or(regop) => {
                 self.or(Register8Bit::A, self.get_reg(regop));
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::OrFromMem() => {
                 self.or(Register8Bit::A, self.read_byte(self.get_reg_16(Register16Bit::HL)));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::OrImm() => {
-                self.or(Register8Bit::A, self.read_byte(pc + 1));
-                (pc + 2, 2)
+                self.or(Register8Bit::A, self.read_byte(pc.wrapping_add(1)));
+                (pc.wrapping_add(2), 2)
             },
             Instruction::Xor(regop) => {
                 self.xor(Register8Bit::A, self.get_reg(regop));
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::XorFromMem() => {
                 self.xor(Register8Bit::A, self.read_byte(self.get_reg_16(Register16Bit::HL)));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::XorImm() => {
-                self.xor(Register8Bit::A, self.read_byte(pc + 1));
-                (pc + 2, 2)
+                self.xor(Register8Bit::A, self.read_byte(pc.wrapping_add(1)));
+                (pc.wrapping_add(2), 2)
             },
             Instruction::Cp(regop) => {
                 self.cp(Register8Bit::A, self.get_reg(regop));
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::CpFromMem() => {
                 self.cp(Register8Bit::A, self.read_byte(self.get_reg_16(Register16Bit::HL)));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::CpImm() => {
-                self.cp(Register8Bit::A, self.read_byte(pc + 1));
-                (pc + 2, 2)
+                self.cp(Register8Bit::A, self.read_byte(pc.wrapping_add(1)));
+                (pc.wrapping_add(2), 2)
             },
             Instruction::Rla() => {
                 self.rotate_left(Register8Bit::A, false, false);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Rlca() => {
                 self.rotate_left(Register8Bit::A, true, false);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Rra() => {
                 self.rotate_right(Register8Bit::A, false, false);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Rrca() => {
                 self.rotate_right(Register8Bit::A, true, false);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Add(regop) => {
                 self.add(Register8Bit::A, self.get_reg(regop), false);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Adc(regop) => {
                 self.add(Register8Bit::A, self.get_reg(regop), true);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::AddImm() => {
-                self.add(Register8Bit::A, self.read_byte(pc + 1), false);
-                (pc + 2, 2)
+                self.add(Register8Bit::A, self.read_byte(pc.wrapping_add(1)), false);
+                (pc.wrapping_add(2), 2)
             },
             Instruction::AddFromMem() => {
                 self.add(Register8Bit::A, self.read_byte(self.get_reg_16(Register16Bit::HL)), false);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::Add16(pair) => {
                 self.add_16(pair);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::AddSpS8() => {
-                let op = self.read_byte(pc + 1) as i8;
+                let op = self.read_byte(pc.wrapping_add(1)) as i8;
                 self.add_sp_s8(op);
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             },
             Instruction::AdcFromMem() => {
                 self.add(Register8Bit::A, self.read_byte(self.get_reg_16(Register16Bit::HL)), true);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::Sub(regop) => {
                 self.subtract(Register8Bit::A, self.get_reg(regop), false);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Sbc(regop) => {
                 self.subtract(Register8Bit::A, self.get_reg(regop), true);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::SubImm() => {
-                self.subtract(Register8Bit::A, self.read_byte(pc + 1), false);
-                (pc + 2, 2)
+                self.subtract(Register8Bit::A, self.read_byte(pc.wrapping_add(1)), false);
+                (pc.wrapping_add(2), 2)
             },
             Instruction::SubFromMem() => {
                 self.subtract(Register8Bit::A, self.read_byte(self.get_reg_16(Register16Bit::HL)), false);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::SbcFromMem() => {
                 self.subtract(Register8Bit::A, self.read_byte(self.get_reg_16(Register16Bit::HL)), true);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::AdcAD8() => {
-                let operand = self.read_byte(pc + 1);
+                let operand = self.read_byte(pc.wrapping_add(1));
                 self.add(Register8Bit::A, operand, true);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             Instruction::SbcAD8() => {
-                let operand = self.read_byte(pc + 1);
+                let operand = self.read_byte(pc.wrapping_add(1));
                 self.subtract(Register8Bit::A, operand, true);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             Instruction::LdRegister(dest, src) => {
+                if self.breakpoint_enabled && matches!((dest, src), (Register8Bit::B, Register8Bit::B)) {
+                    self.breakpoint_hit = true;
+                }
+
                 self.load_register(dest, src);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::LdImm(regop) => {
-                self.set_reg(regop, self.read_byte(pc + 1));
-                (pc + 2, 2)
+                self.set_reg(regop, self.read_byte(pc.wrapping_add(1)));
+                (pc.wrapping_add(2), 2)
             },
             Instruction::LdToMem(regop, pair) => {
                 self.ld_to_mem(regop, self.get_reg_16(pair));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::LdFromMem(regop, pair) => {
                 self.ld_from_mem(regop, self.get_reg_16(pair));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::LdToMemInc() => {
                 self.ld_to_mem(Register8Bit::A, self.get_reg_16(Register16Bit::HL));
                 self.set_reg_16(Register16Bit::HL, self.get_reg_16(Register16Bit::HL).wrapping_add(1));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::LdToMemDec() => {
                 self.ld_to_mem(Register8Bit::A, self.get_reg_16(Register16Bit::HL));
                 self.set_reg_16(Register16Bit::HL, self.get_reg_16(Register16Bit::HL).wrapping_sub(1));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::LdFromMemInc() => {
                 self.ld_from_mem(Register8Bit::A, self.get_reg_16(Register16Bit::HL));
                 self.set_reg_16(Register16Bit::HL, self.get_reg_16(Register16Bit::HL).wrapping_add(1));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::LdFromMemDec() => {
                 self.ld_from_mem(Register8Bit::A, self.get_reg_16(Register16Bit::HL));
                 self.set_reg_16(Register16Bit::HL, self.get_reg_16(Register16Bit::HL).wrapping_sub(1));
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::LdRegister16Imm(pair) => {
-                self.set_reg_16(pair, self.read_word(pc + 1));
-                (pc + 3, 3)
+                self.set_reg_16(pair, self.read_word(pc.wrapping_add(1)));
+                (pc.wrapping_add(3), 3)
             },
             Instruction::LdToImmUpperMem() => {
-                let offset = self.read_byte(pc + 1);
+                let offset = self.read_byte(pc.wrapping_add(1));
                 self.ld_to_mem(Register8Bit::A, 0xff00 + (offset as u16));
-                (pc + 2, 3)
+                (pc.wrapping_add(2), 3)
             },
             Instruction::LdFromImmUpperMem() => {
-                let offset = self.read_byte(pc + 1);
+                let offset = self.read_byte(pc.wrapping_add(1));
                 self.ld_from_mem(Register8Bit::A, 0xff00 + (offset as u16));
-                (pc + 2, 3)
+                (pc.wrapping_add(2), 3)
             },
             Instruction::LdToImmMem() => {
-                let addr = self.read_word(pc + 1);
+                let addr = self.read_word(pc.wrapping_add(1));
                 self.ld_to_mem(Register8Bit::A, addr);
-                (pc + 3, 4)
+                (pc.wrapping_add(3), 4)
             },
             Instruction::LdFromImmMem() => {
-                let addr = self.read_word(pc + 1);
+                let addr = self.read_word(pc.wrapping_add(1));
                 self.ld_from_mem(Register8Bit::A, addr);
-                (pc + 3, 4)
+                (pc.wrapping_add(3), 4)
             },
             Instruction::LdToCUpperMem() => {
                 let offset = self.get_reg(Register8Bit::C) as u16;
                 self.ld_to_mem(Register8Bit::A, 0xff00 + offset);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::LdFromCUpperMem() => {
                 let offset = self.get_reg(Register8Bit::C) as u16;
                 self.ld_from_mem(Register8Bit::A, 0xff00 + offset);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::LdHlToSp() => {
                 let hl = self.get_reg_16(Register16Bit::HL);
                 self.set_reg_16(Register16Bit::SP, hl);
-                (pc + 1, 2)
+                (pc.wrapping_add(1), 2)
             },
             Instruction::LdSpOffsetToHl() => {
                 self.ld_sp_offset_to_hl(pc);
-                (pc + 2, 3)
+                (pc.wrapping_add(2), 3)
             },
             Instruction::LdSpToImmMem() => {
                 self.ld_sp_to_imm_mem(pc);
-                (pc + 3, 5)
+                (pc.wrapping_add(3), 5)
             },
             Instruction::LdToMemImm() => {
-                let operand = self.read_byte(pc + 1);
+                let operand = self.read_byte(pc.wrapping_add(1));
                 let addr = self.get_reg_16(Register16Bit::HL);
                 self.write_byte(addr, operand);
-                (pc + 2, 3)
+                (pc.wrapping_add(2), 3)
             },
             Instruction::JumpAbs(condition) => {
+                // the operand is fetched whether or not the jump is taken
+                let addr = self.read_word(pc.wrapping_add(1));
                 if self.should_branch(condition) {
-                    let addr = (self.read_byte(pc + 1) as u16)
-                               | ((self.read_byte(pc + 2) as u16) << 8);
                     (addr, 4)
                 } else {
-                    (pc + 3, 3)
+                    (pc.wrapping_add(3), 3)
                 }
             },
             Instruction::JumpAbsFromReg() => {
                 (self.get_reg_16(Register16Bit::HL), 1)
             },
             Instruction::JumpRel(condition) => {
+                let offset = self.read_byte(pc.wrapping_add(1)) as i8;
                 if self.should_branch(condition) {
                     // cast as offset i8 to preserve sign
                     // cast PC as i32 to ensure unsigned
                     // cast offset as i32 for arithmetic
-                    let offset = self.read_byte(pc + 1) as i8;
                     let addr = ((pc as i32) + (offset as i32) + 2) as u16;
                     (addr, 3)
                 } else {
-                    (pc + 3, 2)
+                    (pc.wrapping_add(2), 2)
                 }
             },
             Instruction::Push(pair) => {
                 let val = self.get_reg_16(pair);
                 self.push(val);
-                (pc + 1, 4)
+                (pc.wrapping_add(1), 4)
             },
             Instruction::Pop(pair) => {
-                let val = self.pop();
+                let mut val = self.pop();
+
+                // the low nibble of F doesn't exist and always reads 0
+                if matches!(pair, Register16Bit::AF) {
+                    val &= 0xfff0;
+                }
                 self.set_reg_16(pair, val);
-                (pc + 1, 3)
+                (pc.wrapping_add(1), 3)
             },
             Instruction::Ret(condition) => {
                 if self.should_branch(condition) {
                     (self.pop(), if matches!(condition, BranchCondition::NONE) { 4 } else { 5 })
                 } else {
-                    (pc + 1, 2)
+                    (pc.wrapping_add(1), 2)
                 }
             },
+            Instruction::Reti() => {
+                // unlike EI this takes effect immediately
+                self.set_ime(true);
+                (self.pop(), 4)
+            },
             Instruction::Rst(vec) => {
-                self.push(pc);
+                self.push(pc.wrapping_add(1));
                 (vec as u16, 4)
             },
             Instruction::Call(condition) => {
+                let addr = self.read_word(pc.wrapping_add(1));
                 if self.should_branch(condition) {
-                    self.push(pc + 2);
-                    (self.read_word(pc + 1), 6)
+                    self.push(pc.wrapping_add(3));
+                    (addr, 6)
                 } else {
-                    (pc + 3, 3)
+                    (pc.wrapping_add(3), 3)
                 }
             },
             Instruction::ToggleCarryFlag() => {
                 self.toggle_carry();
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::SetCarryFlag() => {
                 self.set_carry();
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Invert() => {
                 self.invert(Register8Bit::A);
-                (pc + 1, 1)
+                (pc.wrapping_add(1), 1)
+            },
+            Instruction::Daa() => {
+                self.daa();
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Stop() => {
                 self.stopped = true;
-                (pc + 2, 1)
+                (pc.wrapping_add(2), 1)
             },
             Instruction::Halt() => {
-                self.halted = true;
-                (pc + 1, 1)
+                if self.pending_interrupts() == 0 {
+                    self.halted = true;
+                } else if !self.ime() {
+                    // with IME off and an interrupt already pending HALT
+                    // exits straight away, but the CPU fails to increment
+                    // PC after fetching the next opcode so that byte gets
+                    // read twice
+                    self.halt_bug = true;
+                } else if self.ei_taking_effect {
+                    // EI; HALT with an interrupt pending is the same bug,
+                    // the interrupt is serviced right away and returns to
+                    // the HALT
+                    return (pc, 1);
+                }
+                (pc.wrapping_add(1), 1)
             },
-            // there is no good way to write a unit test for these,
-            // skip them in testing
             Instruction::Ei() => {
-                self.mmu.borrow_mut().intc.set_ime(true);
-                (pc + 1, 1)
+                self.ei_pending = true;
+                (pc.wrapping_add(1), 1)
             },
             Instruction::Di() => {
-                self.mmu.borrow_mut().intc.set_ime(false);
-                (pc + 1, 1)
+                self.set_ime(false);
+                (pc.wrapping_add(1), 1)
             },
             Instruction::CbInstruction() => {
-                let cb_instruction_byte = self.read_byte(self.pc + 1);
+                let cb_instruction_byte = self.read_byte(self.pc.wrapping_add(1));
 
                 match CbInstruction::from_byte(cb_instruction_byte) {
                     Some(instruction) => {
@@ -991,91 +1072,91 @@ impl Cpu {
         match instruction {
             CbInstruction::Rl(regop) => {
                 self.rotate_left(regop, false, true);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             CbInstruction::Rlc(regop) => {
                 self.rotate_left(regop, true, true);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             CbInstruction::RlMem() => {
                 self.rotate_left_mem(false);
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             },
             CbInstruction::RlcMem() => {
                 self.rotate_left_mem(true);
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             },
             CbInstruction::Rr(regop) => {
                 self.rotate_right(regop, false, true);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             CbInstruction::Rrc(regop) => {
                 self.rotate_right(regop, true, true);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             CbInstruction::RrMem() => {
                 self.rotate_right_mem(false);
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             },
             CbInstruction::RrcMem() => {
                 self.rotate_right_mem(true);
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             },
             CbInstruction::Sla(regop) => {
                 self.shift_left(regop);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             }
             CbInstruction::SlaMem() => {
                 self.shift_left_mem();
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             }
             CbInstruction::Sra(regop) => {
                 self.shift_right(regop, false); 
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             }
             CbInstruction::SraMem() => {
                 self.shift_right_mem(false); 
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             }
             CbInstruction::Srl(regop) => {
                 self.shift_right(regop, true); 
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             }
             CbInstruction::SrlMem() => {
                 self.shift_right_mem(true); 
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             }
             CbInstruction::Res(regop, shift) => {
                 self.clear_bit(regop, shift);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             CbInstruction::Bit(regop, bit) => {
                 self.check_bit(regop, bit);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             CbInstruction::Set(regop, bit) => {
                 self.set_bit(regop, bit);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             CbInstruction::ResMem(bit) => {
                 self.clear_bit_from_mem(bit);
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             },
             CbInstruction::BitMem(bit) => {
                 self.check_bit_from_mem(bit);
-                (pc + 2, 3)
+                (pc.wrapping_add(2), 3)
             },
             CbInstruction::SetMem(bit) => {
                 self.set_bit_from_mem(bit);
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             },
             CbInstruction::Swap(regop) => {
                 self.swap(regop);
-                (pc + 2, 2)
+                (pc.wrapping_add(2), 2)
             },
             CbInstruction::SwapMem() => {
                 self.swap_mem();
-                (pc + 2, 4)
+                (pc.wrapping_add(2), 4)
             },
             _ => panic!("Invalid cb instruction"),
         }
@@ -1101,18 +1182,60 @@ impl Cpu {
                 self.get_flag(Flag::C))
     }
 
-    pub fn step(&mut self) {
+    // dispatch a pending interrupt or execute a single instruction,
+    // returning the number of M-cycles used
+    pub fn step(&mut self) -> usize {
+        // a locked up CPU never fetches again, but time keeps passing
+        if self.locked {
+            return 1;
+        }
+
+        let pending = self.pending_interrupts();
+
+        // any enabled interrupt being requested ends HALT, even if IME is
+        // off. a halted CPU executes nothing but the rest of the system
+        // keeps running, so count it as a single M-cycle
+        if self.halted {
+            if pending == 0 {
+                return 1;
+            }
+            self.halted = false;
+        }
+
+        if pending != 0 && self.ime() {
+            return self.dispatch_interrupt();
+        }
+
+        // EI takes effect after the instruction following it, so the
+        // earliest an interrupt can be dispatched is after that instruction
+        self.ei_taking_effect = self.ei_pending;
+        if self.ei_pending {
+            self.ei_pending = false;
+            self.set_ime(true);
+        }
+
         let instruction_byte = self.read_byte(self.pc);
 
+        // execute as if the opcode was one byte earlier, so that operands
+        // are read starting from the opcode itself
+        if self.halt_bug {
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
+        }
+
         match Instruction::from_byte(instruction_byte) {
             Some(instruction) => {
                 let (new_pc, cycles) = self.execute_instruction(instruction);
                 self.pc = new_pc;
                 self.cycles += cycles;
+                cycles
             },
             None => {
-                panic!("invalid instruction read from ROM at {:#06x}: {:#04x}\n{}",
-                       self.pc, instruction_byte, self.dump_to_string());
+                // the illegal opcodes hang the CPU until it is reset, not
+                // even interrupts get it going again
+                self.locked = true;
+                self.cycles += 1;
+                1
             },
         }
     }
@@ -1142,35 +1265,102 @@ impl Cpu {
 
         self.stopped = false;
         self.halted = false;
+        self.halt_bug = false;
+        self.ei_pending = false;
+        self.ei_taking_effect = false;
+        self.locked = false;
     }
 
     pub fn stopped(&self) -> bool {
         self.stopped
     }
 
+    // hung by an illegal opcode, only a reset gets it going again. PC is
+    // left pointing at the offending opcode
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn exit_stop(&mut self) {
+        self.stopped = false;
+    }
+
+    #[allow(dead_code)]
     pub fn halted(&self) -> bool {
         self.halted
     }
 
-    pub fn exit_halt(&mut self) {
-        self.halted = false;
+    // treat LD B,B as a software breakpoint, the way test ROMs such as
+    // mooneye's signal that they've finished
+    pub fn set_ld_b_b_breakpoint(&mut self, enabled: bool) {
+        self.breakpoint_enabled = enabled;
+        self.breakpoint_hit = false;
+    }
+
+    // true if LD B,B has been executed since the last call
+    pub fn check_and_consume_breakpoint(&mut self) -> bool {
+        let hit = self.breakpoint_hit;
+        self.breakpoint_hit = false;
+        hit
     }
 
-    pub fn trigger_interrupt(&mut self, interrupt: Interrupt) {
-        self.push(self.pc);
-        self.pc = interrupt.vector();
+    // IE & IF, the interrupts that are both enabled and requested
+    fn pending_interrupts(&self) -> u8 {
+        self.read_byte(IE_ADDR) & self.read_byte(IF_ADDR) & 0x1f
+    }
+
+    /*
+     * Jump to the highest priority pending interrupt handler, returning the
+     * number of M-cycles used.
+     *
+     * - Two wait states are executed (2 M-cycles pass while nothing occurs, presumably
+     *   the CPU is executing nops during this time).
+     * - The current PC is pushed to the stack, consuming 2 more M-cycles.
+     * - The PC register is set to the address of the handler ($40, $48, $50, $58, $60).
+     *   This consumes one last M-cycle.
+     *
+     * The interrupt is only picked after the high byte of PC has been pushed.
+     * If that push lands on IE (SP = 0x0000) it can disable the interrupt
+     * being dispatched, in which case the next one in line is used, or if
+     * there is none the CPU jumps to 0x0000 without acknowledging anything.
+     */
+    fn dispatch_interrupt(&mut self) -> usize {
+        self.set_ime(false);
+
+        let pc = self.pc;
+        let sp = self.get_sp().wrapping_sub(1);
+        self.write_byte(sp, (pc >> 8) as u8);
+
+        let pending = self.pending_interrupts();
+
+        let sp = sp.wrapping_sub(1);
+        self.write_byte(sp, (pc & 0xff) as u8);
+        self.set_sp(sp);
+
+        self.pc = match Interrupt::iterator().find(|i| (pending & (1 << (*i as u8))) != 0) {
+            Some(interrupt) => {
+                let flag = self.read_byte(IF_ADDR);
+                self.write_byte(IF_ADDR, flag & !(1 << (interrupt as u8)));
+                interrupt.vector()
+            },
+            None => 0x0000,
+        };
 
-        // The following occurs when control is being transferred to an interrupt handler:
-        //
-        // - Two wait states are executed (2 M-cycles pass while nothing occurs, presumably
-        //   the CPU is executing nops during this time).
-        // - The current PC is pushed to the stack, consuming 2 more M-cycles.
-        // - The PC register is set to the address of the handler ($40, $48, $50, $58, $60).
-        //   This consumes one last M-cycle.
         self.cycles += 5;
+        5
+    }
+}
+
+#[cfg(test)]
+impl Cpu<FlatRam> {
+    fn load_test_ram(&mut self, data: &[u8]) {
+        self.bus.load(data);
     }
 }
 
 #[cfg(test)]
 mod test;
 
+#[cfg(test)]
+mod sm83;
+
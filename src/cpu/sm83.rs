@@ -0,0 +1,306 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+/*
+ * SM83 single step tests
+ *
+ * Runs the per-opcode JSON test vectors from
+ * https://github.com/SingleStepTests/sm83 against execute_instruction.
+ * Each file holds a list of cases with the initial and final register and
+ * RAM state, plus the bus activity for every M-cycle. Besides the final
+ * state the cycle count is checked, and every read and write has to line
+ * up, in order, with the cycles which access the bus. Internal cycles
+ * can't be seen on FlatRam, so only their number is checked.
+ *
+ * The vectors aren't checked in, so test_sm83 is ignored by default. Point
+ * DOOKIEBOY_SM83_TESTS at the directory holding the .json files (v1 in that
+ * repo) to run it:
+ *
+ *   DOOKIEBOY_SM83_TESTS=~/sm83/v1 cargo test sm83 -- --ignored
+ */
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::*;
+use crate::bus::{Access, FlatRam};
+use crate::memory::Memory;
+
+const SM83_DIR_VAR: &str = "DOOKIEBOY_SM83_TESTS";
+
+// only this many failures are printed per opcode
+const MAX_REPORTED: usize = 5;
+
+// STOP and HALT depend on state outside the CPU (joypad, pending
+// interrupts) that the vectors don't describe
+const SKIPPED: [&str; 2] = ["10.json", "76.json"];
+
+const REGS: [(&str, Register8Bit); 8] = [
+    ("a", Register8Bit::A),
+    ("f", Register8Bit::F),
+    ("b", Register8Bit::B),
+    ("c", Register8Bit::C),
+    ("d", Register8Bit::D),
+    ("e", Register8Bit::E),
+    ("h", Register8Bit::H),
+    ("l", Register8Bit::L),
+];
+
+fn test_cpu() -> Cpu<FlatRam> {
+    let mut ram = FlatRam::new();
+    ram.log_accesses(true);
+    Cpu::new(ram)
+}
+
+fn field(state: &Value, name: &str) -> u16 {
+    state[name].as_u64().unwrap_or_else(|| panic!("missing field {}", name)) as u16
+}
+
+fn ram(state: &Value) -> Vec<(u16, u8)> {
+    state["ram"].as_array().unwrap().iter()
+        .map(|entry| (entry[0].as_u64().unwrap() as u16, entry[1].as_u64().unwrap() as u8))
+        .collect()
+}
+
+// the access made in one M-cycle of the bus activity, each entry is
+// [address, value, "r-m"/"-wm"] or null for an internal cycle
+fn access(cycle: &Value) -> Option<Access> {
+    let kind = cycle[2].as_str()?;
+    let addr = cycle[0].as_u64().unwrap() as u16;
+    let val = cycle[1].as_u64().unwrap() as u8;
+
+    if kind.contains('w') {
+        Some(Access::Write(addr, val))
+    } else if kind.contains('r') {
+        Some(Access::Read(addr, val))
+    } else {
+        None
+    }
+}
+
+// the accesses have to match the cycles which use the bus one for one
+fn check_bus(cycles: &[Value], accesses: &[Access], errors: &mut Vec<String>) {
+    let mut actual = accesses.iter();
+
+    for (n, cycle) in cycles.iter().enumerate() {
+        let expected = match access(cycle) {
+            Some(expected) => expected,
+            None => continue,
+        };
+
+        match actual.next() {
+            Some(access) if *access == expected => {},
+            access => {
+                errors.push(format!("cycle {}: {:x?} expected {:x?}", n, access, expected));
+                return;
+            },
+        }
+    }
+
+    let extra: Vec<_> = actual.collect();
+    if !extra.is_empty() {
+        errors.push(format!("extra accesses: {:x?}", extra));
+    }
+}
+
+fn load_state(cpu: &mut Cpu<FlatRam>, state: &Value) {
+    for (name, reg) in REGS.iter() {
+        cpu.set_reg(*reg, field(state, name) as u8);
+    }
+
+    cpu.pc = field(state, "pc");
+    cpu.set_sp(field(state, "sp"));
+    cpu.set_ime(field(state, "ime") != 0);
+
+    cpu.halted = false;
+    cpu.halt_bug = false;
+    cpu.ei_pending = false;
+    cpu.ei_taking_effect = false;
+
+    for (addr, val) in ram(state) {
+        cpu.bus.mem_write_byte(addr, val);
+    }
+    cpu.bus.take_accesses();
+}
+
+fn check_state(cpu: &Cpu<FlatRam>, state: &Value, errors: &mut Vec<String>) {
+    for (name, reg) in REGS.iter() {
+        let expected = field(state, name) as u8;
+        let actual = cpu.get_reg(*reg);
+        if actual != expected {
+            errors.push(format!("{}: {:#04x} expected {:#04x}", name, actual, expected));
+        }
+    }
+
+    if cpu.pc != field(state, "pc") {
+        errors.push(format!("pc: {:#06x} expected {:#06x}", cpu.pc, field(state, "pc")));
+    }
+
+    if cpu.get_sp() != field(state, "sp") {
+        errors.push(format!("sp: {:#06x} expected {:#06x}", cpu.get_sp(), field(state, "sp")));
+    }
+
+    // some versions of the vectors report a pending EI separately, others
+    // just show IME as set
+    let (ime, ei) = match state.get("ei") {
+        Some(ei) => (cpu.ime(), Some((cpu.ei_pending, ei.as_u64() == Some(1)))),
+        None => (cpu.ime() || cpu.ei_pending, None),
+    };
+
+    if ime != (field(state, "ime") != 0) {
+        errors.push(format!("ime: {} expected {}", ime, !ime));
+    }
+
+    if let Some((actual, expected)) = ei {
+        if actual != expected {
+            errors.push(format!("ei: {} expected {}", actual, expected));
+        }
+    }
+
+    for (addr, val) in ram(state) {
+        let actual = cpu.bus.mem_read_byte(addr);
+        if actual != val {
+            errors.push(format!("ram[{:#06x}]: {:#04x} expected {:#04x}", addr, actual, val));
+        }
+    }
+}
+
+// run a single case, returning what didn't match
+fn run_case(cpu: &mut Cpu<FlatRam>, case: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    load_state(cpu, &case["initial"]);
+
+    let opcode = cpu.read_byte(cpu.pc);
+    let instruction = match Instruction::from_byte(opcode) {
+        Some(i) => i,
+        None => return vec![format!("illegal opcode {:#04x}", opcode)],
+    };
+
+    let (pc, cycles) = cpu.execute_instruction(instruction);
+    cpu.pc = pc;
+    let accesses = cpu.bus.take_accesses();
+
+    check_state(cpu, &case["final"], &mut errors);
+
+    let bus = case["cycles"].as_array().unwrap();
+    if cycles != bus.len() {
+        errors.push(format!("cycles: {} expected {}", cycles, bus.len()));
+    }
+
+    check_bus(bus, &accesses, &mut errors);
+
+    // leave RAM zeroed for the next case
+    let written = accesses.iter().filter_map(|access| match access {
+        Access::Write(addr, val) => Some((*addr, *val)),
+        Access::Read(..) => None,
+    });
+    let touched = ram(&case["initial"]).into_iter()
+        .chain(ram(&case["final"]))
+        .chain(written);
+    for (addr, _) in touched {
+        cpu.bus.mem_write_byte(addr, 0);
+    }
+
+    errors
+}
+
+// returns the number of failing cases
+fn run_file(path: &Path) -> usize {
+    let data = fs::read_to_string(path).unwrap();
+    let cases: Value = serde_json::from_str(&data)
+        .unwrap_or_else(|e| panic!("unable to parse {}: {}", path.display(), e));
+
+    let mut cpu = test_cpu();
+    let mut failed = 0;
+
+    for case in cases.as_array().unwrap() {
+        let errors = run_case(&mut cpu, case);
+        if errors.is_empty() {
+            continue;
+        }
+
+        if failed < MAX_REPORTED {
+            println!("{}: {}", case["name"].as_str().unwrap_or("?"), errors.join(", "));
+        }
+        failed += 1;
+    }
+
+    if failed > 0 {
+        println!("{}: {} failed", path.display(), failed);
+    }
+
+    failed
+}
+
+// one case each for CALL and ADD SP,s8 in the vectors' format, so the
+// harness itself is exercised without the full set
+const INLINE_CASES: &str = r#"[
+{"name":"cd 0000",
+ "initial":{"pc":4096,"sp":2,"a":0,"b":0,"c":0,"d":0,"e":0,"f":0,"h":0,"l":0,"ime":0,"ie":0,
+            "ram":[[4096,205],[4097,52],[4098,18]]},
+ "final":{"pc":4660,"sp":0,"a":0,"b":0,"c":0,"d":0,"e":0,"f":0,"h":0,"l":0,"ime":0,
+          "ram":[[4096,205],[4097,52],[4098,18],[1,16],[0,3]]},
+ "cycles":[[4096,205,"r-m"],[4097,52,"r-m"],[4098,18,"r-m"],null,[1,16,"-wm"],[0,3,"-wm"]]},
+{"name":"e8 0000",
+ "initial":{"pc":256,"sp":65535,"a":0,"b":0,"c":0,"d":0,"e":0,"f":128,"h":0,"l":0,"ime":0,"ie":0,
+            "ram":[[256,232],[257,255]]},
+ "final":{"pc":258,"sp":65534,"a":0,"b":0,"c":0,"d":0,"e":0,"f":48,"h":0,"l":0,"ime":0,
+          "ram":[[256,232],[257,255]]},
+ "cycles":[[256,232,"r-m"],[257,255,"r-m"],null,null]}
+]"#;
+
+// Verify the harness against the inline cases, including that it notices
+// a wrong value or writes in the wrong order
+#[test]
+fn test_sm83_inline() {
+    let cases: Value = serde_json::from_str(INLINE_CASES).unwrap();
+    let mut cpu = test_cpu();
+
+    for case in cases.as_array().unwrap() {
+        assert_eq!(run_case(&mut cpu, case), Vec::<String>::new());
+    }
+
+    let mut wrong_value = cases[0].clone();
+    wrong_value["final"]["ram"][4][1] = Value::from(4);
+    let errors = run_case(&mut cpu, &wrong_value);
+    assert_eq!(errors, vec!["ram[0x0000]: 0x03 expected 0x04".to_string()]);
+
+    let mut wrong_order = cases[0].clone();
+    wrong_order["cycles"].as_array_mut().unwrap().swap(4, 5);
+    let errors = run_case(&mut cpu, &wrong_order);
+    assert_eq!(errors, vec!["cycle 4: Some(Write(1, 10)) expected Write(0, 3)".to_string()]);
+}
+
+// Verify every opcode against the SM83 single step test vectors
+#[test]
+#[ignore = "needs DOOKIEBOY_SM83_TESTS"]
+fn test_sm83() {
+    let dir = env::var_os(SM83_DIR_VAR)
+        .unwrap_or_else(|| panic!("{} isn't set, see src/cpu/sm83.rs", SM83_DIR_VAR));
+    assert!(Path::new(&dir).is_dir(), "{:?} not found", dir);
+
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("unable to read {:?}: {}", dir, e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter(|path| !SKIPPED.iter().any(|s| path.file_name().unwrap() == *s))
+        .collect();
+    paths.sort();
+
+    let failed_files = paths.iter().filter(|path| run_file(path) > 0).count();
+    assert_eq!(failed_files, 0, "{} of {} opcodes failed", failed_files, paths.len());
+}
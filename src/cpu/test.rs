@@ -12,11 +12,13 @@
 // ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
 
 use super::*;
+use crate::memory::Memory;
+use crate::mmu::Mmu;
 
 // Verify incrementing registers
 #[test]
 fn test_all_increments() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 7;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Inc(Register8Bit::B).as_byte(),
@@ -43,7 +45,7 @@ fn test_all_increments() {
 // Verify overflow when incrementing 0xff
 #[test]
 fn test_increment_overflow() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Inc(Register8Bit::B).as_byte(),
@@ -55,13 +57,13 @@ fn test_increment_overflow() {
     assert_eq!(cpu.get_flag(Flag::Z), true);
     assert_eq!(cpu.get_flag(Flag::N), false);
     assert_eq!(cpu.get_flag(Flag::H), true);
-    assert_eq!(cpu.get_flag(Flag::C), true);
+    assert_eq!(cpu.get_flag(Flag::C), false);
 }
 
 // Verify incrementing 16bit registers
 #[test]
 fn test_increment_16() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 4;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Inc16(Register16Bit::BC).as_byte(),
@@ -92,7 +94,7 @@ fn test_increment_16() {
 // Verify decrementing 16bit registers
 #[test]
 fn test_decrement_16() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 4;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Dec16(Register16Bit::BC).as_byte(),
@@ -123,7 +125,7 @@ fn test_decrement_16() {
 // Verify adding to registers
 #[test]
 fn test_add() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Add(Register8Bit::B).as_byte(),
@@ -152,7 +154,7 @@ fn test_add() {
 // Verify adding to registers with overflow
 #[test]
 fn test_add_overflow() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Add(Register8Bit::B).as_byte(),
@@ -180,7 +182,7 @@ fn test_add_overflow() {
 
 #[test]
 fn test_add_16() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Add16(Register16Bit::BC).as_byte(),
@@ -205,8 +207,10 @@ fn test_add_16() {
     assert_eq!(cpu.get_flag(Flag::N), false);
 }
 
+// Verify ADD SP,s8 takes H and C from the low byte
+#[test]
 fn test_add_sp_s8() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 4;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::AddSpS8().as_byte(),
@@ -235,7 +239,7 @@ fn test_add_sp_s8() {
 // Verify adding to registers with carry
 #[test]
 fn test_add_carry() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Adc(Register8Bit::B).as_byte(),
@@ -265,7 +269,7 @@ fn test_add_carry() {
 // Verify adding to registers with overflow
 #[test]
 fn test_add_carry_overflow() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Adc(Register8Bit::B).as_byte(),
@@ -295,7 +299,7 @@ fn test_add_carry_overflow() {
 // Verify adding immediates
 #[test]
 fn test_add_immediate() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::AddImm().as_byte(),
@@ -313,7 +317,7 @@ fn test_add_immediate() {
 // Verify adding immediates with overflow
 #[test]
 fn test_add_immediate_overflow() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::AddImm().as_byte(),
@@ -332,7 +336,7 @@ fn test_add_immediate_overflow() {
 // Verify adding values from memory
 #[test]
 fn test_add_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::AddFromMem().as_byte(),
@@ -353,7 +357,7 @@ fn test_add_mem() {
 // Verify decrementing registers
 #[test]
 fn test_all_decrements() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 7;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Dec(Register8Bit::B).as_byte(),
@@ -382,7 +386,7 @@ fn test_all_decrements() {
 // Verify subtracting registers
 #[test]
 fn test_sub() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Sub(Register8Bit::B).as_byte(),
@@ -411,7 +415,7 @@ fn test_sub() {
 // Verify subtracting registers with overflow
 #[test]
 fn test_sub_overflow() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Sub(Register8Bit::B).as_byte(),
@@ -440,7 +444,7 @@ fn test_sub_overflow() {
 // Verify subtracting registers with carry
 #[test]
 fn test_sub_carry() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Sbc(Register8Bit::B).as_byte(),
@@ -470,7 +474,7 @@ fn test_sub_carry() {
 // Verify adding to registers with overflow
 #[test]
 fn test_sub_carry_overflow() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Sbc(Register8Bit::B).as_byte(),
@@ -500,7 +504,7 @@ fn test_sub_carry_overflow() {
 // Verify subtracting immediates
 #[test]
 fn test_sub_immediate() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::SubImm().as_byte(),
@@ -519,7 +523,7 @@ fn test_sub_immediate() {
 // Verify subtracting immediates with overflow
 #[test]
 fn test_sub_immediate_overflow() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::SubImm().as_byte(),
@@ -538,7 +542,7 @@ fn test_sub_immediate_overflow() {
 // Verify subtracting immediates
 #[test]
 fn test_sub_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::SubFromMem().as_byte(),
@@ -559,7 +563,7 @@ fn test_sub_mem() {
 // Verify anding registers
 #[test]
 fn test_and() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::And(Register8Bit::B).as_byte(),
@@ -588,7 +592,7 @@ fn test_and() {
 // Verify anding registers and immediates
 #[test]
 fn test_and_imm() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::AndImm().as_byte(),
@@ -609,7 +613,7 @@ fn test_and_imm() {
 // Verify anding registers and memory
 #[test]
 fn test_and_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::AndFromMem().as_byte(),
@@ -632,7 +636,7 @@ fn test_and_mem() {
 // Verify oring registers
 #[test]
 fn test_or() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Or(Register8Bit::B).as_byte(),
@@ -661,7 +665,7 @@ fn test_or() {
 // Verify oring registers and immediates
 #[test]
 fn test_or_imm() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::OrImm().as_byte(),
@@ -682,7 +686,7 @@ fn test_or_imm() {
 // Verify oring registers and memory
 #[test]
 fn test_or_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::OrFromMem().as_byte(),
@@ -705,7 +709,7 @@ fn test_or_mem() {
 // Verify xoring registers
 #[test]
 fn test_xor() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Xor(Register8Bit::B).as_byte(),
@@ -734,7 +738,7 @@ fn test_xor() {
 // Verify xoring registers and immediates
 #[test]
 fn test_xor_imm() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::XorImm().as_byte(),
@@ -755,7 +759,7 @@ fn test_xor_imm() {
 // Verify xoring registers and memory
 #[test]
 fn test_xor_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::XorFromMem().as_byte(),
@@ -778,7 +782,7 @@ fn test_xor_mem() {
 // Verify comparing registers
 #[test]
 fn test_cp() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Cp(Register8Bit::B).as_byte(),
@@ -807,7 +811,7 @@ fn test_cp() {
 // Verify comparing immediates
 #[test]
 fn test_cp_immediate() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CpImm().as_byte(),
@@ -826,7 +830,7 @@ fn test_cp_immediate() {
 // Verify rotating
 #[test]
 fn test_rotate() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 21;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Rla().as_byte(),
@@ -987,7 +991,7 @@ fn test_rotate() {
 // Verify shifting
 #[test]
 fn test_shift() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 13;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CbInstruction().as_byte(),
@@ -1077,7 +1081,7 @@ fn test_shift() {
 // Verify from memory
 #[test]
 fn test_cp_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CpFromMem().as_byte(),
@@ -1098,7 +1102,7 @@ fn test_cp_mem() {
 // Verify adding values from memory with carry
 #[test]
 fn test_adc_mem_carry() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::AdcFromMem().as_byte(),
@@ -1120,7 +1124,7 @@ fn test_adc_mem_carry() {
 // Verify adding values from memory with carry and overflow
 #[test]
 fn test_adc_mem_carry_overflow() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::AdcFromMem().as_byte(),
@@ -1142,7 +1146,7 @@ fn test_adc_mem_carry_overflow() {
 // Verify subtracting values from memory with carry
 #[test]
 fn test_sbc_mem_carry() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::SbcFromMem().as_byte(),
@@ -1164,7 +1168,7 @@ fn test_sbc_mem_carry() {
 // Verify subtracting values from memory with carry and overflow
 #[test]
 fn test_sbc_mem_carry_overflow() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::SbcFromMem().as_byte(),
@@ -1186,7 +1190,7 @@ fn test_sbc_mem_carry_overflow() {
 // Verify adding immediate operand d8 to A
 #[test]
 fn test_adc_a_d8() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::AdcAD8().as_byte(),
@@ -1205,7 +1209,7 @@ fn test_adc_a_d8() {
 // Verify subtracting immediate operand d8 to A
 #[test]
 fn test_sbc_a_d8() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::SbcAD8().as_byte(),
@@ -1224,7 +1228,7 @@ fn test_sbc_a_d8() {
 // Verify loading register B
 #[test]
 fn test_ld_b_a() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdRegister(Register8Bit::B, Register8Bit::A).as_byte(),
@@ -1238,7 +1242,7 @@ fn test_ld_b_a() {
 // Verify loading immediate to register
 #[test]
 fn test_ld_imm() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 14;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdImm(Register8Bit::B).as_byte(),
@@ -1284,7 +1288,7 @@ fn test_ld_imm() {
 // Verify loading register B to memory offset @ hl
 #[test]
 fn test_ld_to_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdToMem(Register8Bit::B, Register16Bit::HL).as_byte(),
@@ -1302,7 +1306,7 @@ fn test_ld_to_mem() {
 // Verify loading register B from memory offset @ hl
 #[test]
 fn test_ld_from_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdFromMem(Register8Bit::B, Register16Bit::HL).as_byte(),
@@ -1319,7 +1323,7 @@ fn test_ld_from_mem() {
 // Verify ldToMem inc HL
 #[test]
 fn test_ld_to_mem_inc() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdToMemInc().as_byte(),
@@ -1338,7 +1342,7 @@ fn test_ld_to_mem_inc() {
 // Verify ldToMem dec HL
 #[test]
 fn test_ld_to_mem_dec() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdToMemDec().as_byte(),
@@ -1357,7 +1361,7 @@ fn test_ld_to_mem_dec() {
 // Verify ldFromMem inc HL
 #[test]
 fn test_ld_from_mem_inc() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdFromMemInc().as_byte(),
@@ -1375,7 +1379,7 @@ fn test_ld_from_mem_inc() {
 // Verify ldFromMem dec HL
 #[test]
 fn test_ld_from_mem_dec() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdFromMemDec().as_byte(),
@@ -1393,7 +1397,7 @@ fn test_ld_from_mem_dec() {
 // Verify loading 16 bit immediates
 #[test]
 fn test_ld_reg16_imm() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 12;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdRegister16Imm(Register16Bit::BC).as_byte(),
@@ -1428,7 +1432,7 @@ fn test_ld_reg16_imm() {
 // Verify loading to upper RAM
 #[test]
 fn test_ld_to_upper() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdToImmUpperMem().as_byte(),
@@ -1445,7 +1449,7 @@ fn test_ld_to_upper() {
 // Verify loading from upper RAM
 #[test]
 fn test_ld_from_upper() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdFromImmUpperMem().as_byte(),
@@ -1462,7 +1466,7 @@ fn test_ld_from_upper() {
 // Verify loading to a16 immediate
 #[test]
 fn test_ld_to_mem_imm() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdToImmMem().as_byte(),
@@ -1480,7 +1484,7 @@ fn test_ld_to_mem_imm() {
 // Verify loading from a16 immediate
 #[test]
 fn test_ld_from_mem_imm() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdFromImmMem().as_byte(),
@@ -1498,7 +1502,7 @@ fn test_ld_from_mem_imm() {
 // Verify loading A to 0xff00 + C register
 #[test]
 fn test_ld_to_c_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdToCUpperMem().as_byte(),
@@ -1515,7 +1519,7 @@ fn test_ld_to_c_mem() {
 // Verify loading A from 0xff00 + C register
 #[test]
 fn test_ld_from_c_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdFromCUpperMem().as_byte(),
@@ -1532,7 +1536,7 @@ fn test_ld_from_c_mem() {
 // Verify loading contents of HL to SP
 #[test]
 fn test_ld_sp_hl() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdHlToSp().as_byte(),
@@ -1548,7 +1552,7 @@ fn test_ld_sp_hl() {
 // Verify loading contents of SP + s8 to HL
 #[test]
 fn test_ld_hl_sp_plus_s8() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 8;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdSpOffsetToHl().as_byte(),
@@ -1592,7 +1596,7 @@ fn test_ld_hl_sp_plus_s8() {
 // Verify loading sp in memory
 #[test]
 fn test_ld_sp_imm_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdSpToImmMem().as_byte(),
@@ -1610,7 +1614,7 @@ fn test_ld_sp_imm_mem() {
 // Verify loading d8 into offset (HL)
 #[test]
 fn test_ld_d8_to_mem_imm() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::LdToMemImm().as_byte(),
@@ -1627,7 +1631,7 @@ fn test_ld_d8_to_mem_imm() {
 // Verify noop
 #[test]
 fn test_noop() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Noop().as_byte(),
@@ -1647,7 +1651,7 @@ fn test_noop() {
 // Verify jump nz
 #[test]
 fn test_jp_nz() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpAbs(BranchCondition::NZ).as_byte(),
@@ -1669,7 +1673,7 @@ fn test_jp_nz() {
 // Verify jump z
 #[test]
 fn test_jp_z() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpAbs(BranchCondition::Z).as_byte(),
@@ -1691,7 +1695,7 @@ fn test_jp_z() {
 // Verify jump nc
 #[test]
 fn test_jp_nc() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpAbs(BranchCondition::NC).as_byte(),
@@ -1713,7 +1717,7 @@ fn test_jp_nc() {
 // Verify jump c
 #[test]
 fn test_jp_c() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpAbs(BranchCondition::C).as_byte(),
@@ -1735,7 +1739,7 @@ fn test_jp_c() {
 // Verify jump
 #[test]
 fn test_jp() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpAbs(BranchCondition::NONE).as_byte(),
@@ -1751,7 +1755,7 @@ fn test_jp() {
 // Verify jump HL
 #[test]
 fn test_jp_hl() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpAbsFromReg().as_byte(),
@@ -1766,7 +1770,7 @@ fn test_jp_hl() {
 // Verify jump relative nz
 #[test]
 fn test_jr_nz() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 4;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpRel(BranchCondition::NZ).as_byte(),
@@ -1783,13 +1787,13 @@ fn test_jr_nz() {
     cpu.pc = 0;
     cpu.set_flag(Flag::Z, true);
     cpu.step();
-    assert_ne!(cpu.pc, 2);
+    assert_eq!(cpu.pc, 2);
 }
 
 // Verify jump relative z
 #[test]
 fn test_jr_z() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 4;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpRel(BranchCondition::Z).as_byte(),
@@ -1806,13 +1810,13 @@ fn test_jr_z() {
     cpu.pc = 0;
     cpu.set_flag(Flag::Z, false);
     cpu.step();
-    assert_ne!(cpu.pc, 2);
+    assert_eq!(cpu.pc, 2);
 }
 
 // Verify jump relative nc
 #[test]
 fn test_jr_nc() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 4;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpRel(BranchCondition::NC).as_byte(),
@@ -1829,13 +1833,13 @@ fn test_jr_nc() {
     cpu.pc = 0;
     cpu.set_flag(Flag::C, true);
     cpu.step();
-    assert_ne!(cpu.pc, 2);
+    assert_eq!(cpu.pc, 2);
 }
 
 // Verify jump relative c
 #[test]
 fn test_jr_c() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 4;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpRel(BranchCondition::C).as_byte(),
@@ -1852,13 +1856,13 @@ fn test_jr_c() {
     cpu.pc = 0;
     cpu.set_flag(Flag::C, false);
     cpu.step();
-    assert_ne!(cpu.pc, 2);
+    assert_eq!(cpu.pc, 2);
 }
 
 // Verify jump relative
 #[test]
 fn test_jr() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 4;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::JumpRel(BranchCondition::NONE).as_byte(),
@@ -1875,7 +1879,7 @@ fn test_jr() {
 // Verify push
 #[test]
 fn test_push() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Push(Register16Bit::BC).as_byte(),
@@ -1904,7 +1908,7 @@ fn test_push() {
 // Verify pop
 #[test]
 fn test_pop() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Pop(Register16Bit::BC).as_byte(),
@@ -1935,13 +1939,13 @@ fn test_pop() {
     cpu.set_sp((INSTRUCTIONS_LEN - 2) as u16);
     cpu.set_reg_16(Register16Bit::AF, 0x0000);
     cpu.step();
-    assert_eq!(cpu.get_reg_16(Register16Bit::AF), 0xa55a);
+    assert_eq!(cpu.get_reg_16(Register16Bit::AF), 0xa550);
 }
 
 // Verify ret nz
 #[test]
 fn test_ret_nz() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Ret(BranchCondition::NZ).as_byte(),
@@ -1965,7 +1969,7 @@ fn test_ret_nz() {
 // Verify ret z
 #[test]
 fn test_ret_z() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Ret(BranchCondition::Z).as_byte(),
@@ -1990,7 +1994,7 @@ fn test_ret_z() {
 // Verify ret nc
 #[test]
 fn test_ret_nc() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Ret(BranchCondition::NC).as_byte(),
@@ -2014,7 +2018,7 @@ fn test_ret_nc() {
 // Verify ret c
 #[test]
 fn test_ret_c() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Ret(BranchCondition::C).as_byte(),
@@ -2038,7 +2042,7 @@ fn test_ret_c() {
 // Verify ret
 #[test]
 fn test_ret() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 3;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Ret(BranchCondition::NONE).as_byte(),
@@ -2055,7 +2059,7 @@ fn test_ret() {
 // Verify rst
 #[test]
 fn test_rst() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 10;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Rst(RstVec::ZERO).as_byte(),
@@ -2078,55 +2082,55 @@ fn test_rst() {
     cpu.set_sp(sp_top);
     cpu.step();
     assert_eq!(cpu.pc, RstVec::ZERO as u16);
-    assert_eq!(cpu.read_word(sp_top - 2), 0x0000);
+    assert_eq!(cpu.read_word(sp_top - 2), 0x0001);
 
     cpu.pc = 1;
     cpu.set_sp(sp_top);
     cpu.step();
     assert_eq!(cpu.pc, RstVec::ONE as u16);
-    assert_eq!(cpu.read_word(sp_top - 2), 0x0001);
+    assert_eq!(cpu.read_word(sp_top - 2), 0x0002);
 
     cpu.pc = 2;
     cpu.set_sp(sp_top);
     cpu.step();
     assert_eq!(cpu.pc, RstVec::TWO as u16);
-    assert_eq!(cpu.read_word(sp_top - 2), 0x0002);
+    assert_eq!(cpu.read_word(sp_top - 2), 0x0003);
 
     cpu.pc = 3;
     cpu.set_sp(sp_top);
     cpu.step();
     assert_eq!(cpu.pc, RstVec::THREE as u16);
-    assert_eq!(cpu.read_word(sp_top - 2), 0x0003);
+    assert_eq!(cpu.read_word(sp_top - 2), 0x0004);
 
     cpu.pc = 4;
     cpu.set_sp(sp_top);
     cpu.step();
     assert_eq!(cpu.pc, RstVec::FOUR as u16);
-    assert_eq!(cpu.read_word(sp_top - 2), 0x0004);
+    assert_eq!(cpu.read_word(sp_top - 2), 0x0005);
 
     cpu.pc = 5;
     cpu.set_sp(sp_top);
     cpu.step();
     assert_eq!(cpu.pc, RstVec::FIVE as u16);
-    assert_eq!(cpu.read_word(sp_top - 2), 0x0005);
+    assert_eq!(cpu.read_word(sp_top - 2), 0x0006);
 
     cpu.pc = 6;
     cpu.set_sp(sp_top);
     cpu.step();
     assert_eq!(cpu.pc, RstVec::SIX as u16);
-    assert_eq!(cpu.read_word(sp_top - 2), 0x0006);
+    assert_eq!(cpu.read_word(sp_top - 2), 0x0007);
 
     cpu.pc = 7;
     cpu.set_sp(sp_top);
     cpu.step();
     assert_eq!(cpu.pc, RstVec::SEVEN as u16);
-    assert_eq!(cpu.read_word(sp_top - 2), 0x0007);
+    assert_eq!(cpu.read_word(sp_top - 2), 0x0008);
 }
 
 // Verify call nz
 #[test]
 fn test_call_nz() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 5;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Call(BranchCondition::NZ).as_byte(),
@@ -2141,6 +2145,7 @@ fn test_call_nz() {
     cpu.set_flag(Flag::Z, false);
     cpu.step();
     assert_eq!(cpu.pc, 0xa55a);
+    assert_eq!(cpu.read_word(cpu.get_sp()), 3);
 
     cpu.pc = 0;
     cpu.set_sp(INSTRUCTIONS_LEN as u16);
@@ -2152,7 +2157,7 @@ fn test_call_nz() {
 // Verify call z
 #[test]
 fn test_call_z() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 5;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Call(BranchCondition::Z).as_byte(),
@@ -2179,7 +2184,7 @@ fn test_call_z() {
 // Verify call nc
 #[test]
 fn test_call_nc() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 5;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Call(BranchCondition::NC).as_byte(),
@@ -2205,7 +2210,7 @@ fn test_call_nc() {
 // Verify call c
 #[test]
 fn test_call_c() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 5;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Call(BranchCondition::C).as_byte(),
@@ -2231,7 +2236,7 @@ fn test_call_c() {
 // Verify call
 #[test]
 fn test_call() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 5;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Call(BranchCondition::NONE).as_byte(),
@@ -2250,7 +2255,7 @@ fn test_call() {
 // Verify setting carry flag
 #[test]
 fn test_scf() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::SetCarryFlag().as_byte(),
@@ -2264,7 +2269,7 @@ fn test_scf() {
 // Verify toggling carry flag
 #[test]
 fn test_ccf() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 2;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::ToggleCarryFlag().as_byte(),
@@ -2284,7 +2289,7 @@ fn test_ccf() {
 // Verify CPL
 #[test]
 fn test_cpl() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Invert().as_byte(),
@@ -2302,7 +2307,7 @@ fn test_cpl() {
 // Verify stop
 #[test]
 fn test_stop() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Stop().as_byte(),
@@ -2312,12 +2317,40 @@ fn test_stop() {
 
     cpu.step();
     assert_eq!(cpu.stopped(), true);
+
+    cpu.exit_stop();
+    assert!(!cpu.stopped());
+    assert_eq!(cpu.pc, 2);
+}
+
+// Verify LD B,B only reports a breakpoint when enabled
+#[test]
+fn test_ld_b_b_breakpoint() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    const INSTRUCTIONS_LEN: usize = 3;
+    let test_ram: [u8; INSTRUCTIONS_LEN] = [
+        Instruction::LdRegister(Register8Bit::B, Register8Bit::B).as_byte(),
+        Instruction::LdRegister(Register8Bit::B, Register8Bit::C).as_byte(),
+        Instruction::LdRegister(Register8Bit::B, Register8Bit::B).as_byte(),
+    ];
+
+    cpu.load_test_ram(&test_ram);
+
+    cpu.step();
+    assert!(!cpu.check_and_consume_breakpoint());
+
+    cpu.set_ld_b_b_breakpoint(true);
+    cpu.step();
+    assert!(!cpu.check_and_consume_breakpoint());
+    cpu.step();
+    assert!(cpu.check_and_consume_breakpoint());
+    assert!(!cpu.check_and_consume_breakpoint());
 }
 
 // Verify halt
 #[test]
 fn test_halt() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 1;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::Halt().as_byte(),
@@ -2328,14 +2361,16 @@ fn test_halt() {
     cpu.step();
     assert_eq!(cpu.halted(), true);
 
-    cpu.exit_halt();
-    assert_eq!(cpu.halted(), false);
+    // nothing pending, stays halted
+    assert_eq!(cpu.step(), 1);
+    assert_eq!(cpu.halted(), true);
+    assert_eq!(cpu.pc, 1);
 }
 
 // Verify Res
 #[test]
 fn test_res() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 96;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CbInstruction().as_byte(),
@@ -2540,7 +2575,7 @@ fn test_res() {
 // Verify Bit
 #[test]
 fn test_bit() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 96;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CbInstruction().as_byte(),
@@ -2745,7 +2780,7 @@ fn test_bit() {
 // Verify Set
 #[test]
 fn test_set() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 96;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CbInstruction().as_byte(),
@@ -2950,7 +2985,7 @@ fn test_set() {
 // Verify reset bit from mem
 #[test]
 fn test_reset_from_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 17;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CbInstruction().as_byte(),
@@ -2996,7 +3031,7 @@ fn test_reset_from_mem() {
 // Verify check bit from mem
 #[test]
 fn test_bit_from_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 17;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CbInstruction().as_byte(),
@@ -3057,7 +3092,7 @@ fn test_bit_from_mem() {
 // Verify set bit from mem
 #[test]
 fn test_set_from_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 17;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CbInstruction().as_byte(),
@@ -3102,7 +3137,7 @@ fn test_set_from_mem() {
 
 #[test]
 fn test_swap() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 4;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CbInstruction().as_byte(),
@@ -3132,7 +3167,7 @@ fn test_swap() {
 
 #[test]
 fn test_swap_mem() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
+    let mut cpu = Cpu::new(FlatRam::new());
     const INSTRUCTIONS_LEN: usize = 6;
     let test_ram: [u8; INSTRUCTIONS_LEN] = [
         Instruction::CbInstruction().as_byte(),
@@ -3164,46 +3199,392 @@ fn test_swap_mem() {
 
 #[test]
 fn test_trigger_interrupt() {
-    let mut cpu = Cpu::new(Rc::new(RefCell::new(Mmu::new())));
-
-    cpu.reset();
-    cpu.trigger_interrupt(Interrupt::VBLANK);
-    let stack_top = cpu.get_reg_16(Register16Bit::SP);
-    let old_pc = cpu.read_word(stack_top);
-    assert_eq!(old_pc, 0x0100);
-    assert_eq!(cpu.pc, 0x0040);
-    assert_eq!(cpu.cycles, 5);
-
-    cpu.reset();
-    cpu.trigger_interrupt(Interrupt::STAT);
-    let stack_top = cpu.get_reg_16(Register16Bit::SP);
-    let old_pc = cpu.read_word(stack_top);
-    assert_eq!(old_pc, 0x0100);
-    assert_eq!(cpu.pc, 0x0048);
-    assert_eq!(cpu.cycles, 5);
-
-
-    cpu.reset();
-    cpu.trigger_interrupt(Interrupt::TIMER);
-    let stack_top = cpu.get_reg_16(Register16Bit::SP);
-    let old_pc = cpu.read_word(stack_top);
-    assert_eq!(old_pc, 0x0100);
-    assert_eq!(cpu.pc, 0x0050);
-    assert_eq!(cpu.cycles, 5);
-
-    cpu.reset();
-    cpu.trigger_interrupt(Interrupt::SERIAL);
-    let stack_top = cpu.get_reg_16(Register16Bit::SP);
-    let old_pc = cpu.read_word(stack_top);
-    assert_eq!(old_pc, 0x0100);
-    assert_eq!(cpu.pc, 0x0058);
-    assert_eq!(cpu.cycles, 5);
-
-    cpu.reset();
-    cpu.trigger_interrupt(Interrupt::JOYPAD);
-    let stack_top = cpu.get_reg_16(Register16Bit::SP);
-    let old_pc = cpu.read_word(stack_top);
-    assert_eq!(old_pc, 0x0100);
-    assert_eq!(cpu.pc, 0x0060);
-    assert_eq!(cpu.cycles, 5);
+    let mut cpu = Cpu::new(FlatRam::new());
+
+    for interrupt in Interrupt::iterator() {
+        cpu.reset();
+        cpu.set_ime(true);
+        cpu.write_byte(IE_ADDR, 0x1f);
+        cpu.write_byte(IF_ADDR, 1 << (interrupt as u8));
+        assert_eq!(cpu.step(), 5);
+        let stack_top = cpu.get_reg_16(Register16Bit::SP);
+        let old_pc = cpu.read_word(stack_top);
+        assert_eq!(old_pc, 0x0100);
+        assert_eq!(cpu.pc, interrupt.vector());
+        assert_eq!(cpu.cycles, 5);
+    }
+}
+
+// Verify step reports the M-cycles used by each instruction
+#[test]
+fn test_step_cycles() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    const INSTRUCTIONS_LEN: usize = 9;
+    let test_ram: [u8; INSTRUCTIONS_LEN] = [
+        Instruction::Noop().as_byte(),
+        Instruction::LdRegister16Imm(Register16Bit::BC).as_byte(),
+        0x34,
+        0x12,
+        Instruction::CbInstruction().as_byte(),
+        CbInstruction::SetMem(0).as_byte(),
+        Instruction::JumpAbs(BranchCondition::NONE).as_byte(),
+        0x00,
+        0x00,
+    ];
+
+    cpu.load_test_ram(&test_ram);
+    cpu.set_reg_16(Register16Bit::HL, 0x8000);
+
+    assert_eq!(cpu.step(), 1);
+    assert_eq!(cpu.step(), 3);
+    assert_eq!(cpu.step(), 4);
+    assert_eq!(cpu.step(), 4);
+    assert_eq!(cpu.cycles, 12);
+}
+
+const ILLEGAL_OPCODES: [u8; 11] = [
+    0xd3, 0xdb, 0xdd, 0xe3, 0xe4, 0xeb, 0xec, 0xed, 0xf4, 0xfc, 0xfd,
+];
+
+// Verify every legal opcode decodes and round trips, base and CB
+#[test]
+fn test_all_opcodes_decode() {
+    for byte in 0..=0xffu8 {
+        match Instruction::from_byte(byte) {
+            Some(instruction) => {
+                assert!(!ILLEGAL_OPCODES.contains(&byte), "{:#04x} should be illegal", byte);
+                assert_eq!(instruction.as_byte(), byte, "{:?}", instruction);
+            },
+            None => assert!(ILLEGAL_OPCODES.contains(&byte), "{:#04x} did not decode", byte),
+        }
+    }
+
+    for byte in 0..=0xffu8 {
+        match CbInstruction::from_byte(byte) {
+            Some(instruction) => assert_eq!(instruction.as_byte(), byte, "{:?}", instruction),
+            None => panic!("CB {:#04x} did not decode", byte),
+        }
+    }
+}
+
+// Verify illegal opcodes lock up the CPU instead of panicking
+#[test]
+fn test_illegal_opcode_lockup() {
+    for byte in ILLEGAL_OPCODES.iter() {
+        let mut cpu = Cpu::new(FlatRam::new());
+        cpu.load_test_ram(&[*byte, Instruction::Inc(Register8Bit::B).as_byte()]);
+
+        assert_eq!(cpu.step(), 1);
+        assert!(cpu.locked());
+        assert_eq!(cpu.pc, 0);
+
+        for _ in 0..10 {
+            assert_eq!(cpu.step(), 1);
+        }
+        assert_eq!(cpu.pc, 0);
+        assert_eq!(cpu.get_reg(Register8Bit::B), 0);
+
+        cpu.reset();
+        assert!(!cpu.locked());
+    }
+}
+
+// Verify INC (HL) and DEC (HL) leave carry alone
+#[test]
+fn test_inc_dec_mem() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    let test_ram = [
+        Instruction::IncMem().as_byte(),
+        Instruction::DecMem().as_byte(),
+        Instruction::DecMem().as_byte(),
+    ];
+    cpu.load_test_ram(&test_ram);
+    cpu.set_reg_16(Register16Bit::HL, 0x100);
+    cpu.write_byte(0x100, 0xff);
+    cpu.set_flag(Flag::C, false);
+
+    assert_eq!(cpu.step(), 3);
+    assert_eq!(cpu.read_byte(0x100), 0x00);
+    assert!(cpu.get_flag(Flag::Z));
+    assert!(!cpu.get_flag(Flag::N));
+    assert!(cpu.get_flag(Flag::H));
+    assert!(!cpu.get_flag(Flag::C));
+
+    cpu.set_flag(Flag::C, true);
+    assert_eq!(cpu.step(), 3);
+    assert_eq!(cpu.read_byte(0x100), 0xff);
+    assert!(!cpu.get_flag(Flag::Z));
+    assert!(cpu.get_flag(Flag::N));
+    assert!(cpu.get_flag(Flag::H));
+    assert!(cpu.get_flag(Flag::C));
+
+    cpu.step();
+    assert_eq!(cpu.read_byte(0x100), 0xfe);
+    assert!(!cpu.get_flag(Flag::H));
+}
+
+// Verify DAA after BCD addition and subtraction
+#[test]
+fn test_daa() {
+    // (a, b, subtract, expected result, expected carry)
+    let cases = [
+        (0x15, 0x27, false, 0x42, false),
+        (0x09, 0x01, false, 0x10, false),
+        (0x99, 0x01, false, 0x00, true),
+        (0x50, 0x70, false, 0x20, true),
+        (0x88, 0x88, false, 0x76, true),
+        (0x42, 0x15, true, 0x27, false),
+        (0x10, 0x01, true, 0x09, false),
+        (0x00, 0x01, true, 0x99, true),
+    ];
+
+    for (a, b, subtract, expected, carry) in cases.iter() {
+        let mut cpu = Cpu::new(FlatRam::new());
+        let op = if *subtract {
+            Instruction::Sub(Register8Bit::B).as_byte()
+        } else {
+            Instruction::Add(Register8Bit::B).as_byte()
+        };
+        cpu.load_test_ram(&[op, Instruction::Daa().as_byte()]);
+        cpu.set_reg(Register8Bit::A, *a);
+        cpu.set_reg(Register8Bit::B, *b);
+
+        cpu.step();
+        assert_eq!(cpu.step(), 1);
+        assert_eq!(cpu.get_reg(Register8Bit::A), *expected, "{:#04x} {:#04x}", a, b);
+        assert_eq!(cpu.get_flag(Flag::C), *carry, "{:#04x} {:#04x}", a, b);
+        assert_eq!(cpu.get_flag(Flag::Z), *expected == 0);
+        assert!(!cpu.get_flag(Flag::H));
+        assert_eq!(cpu.get_flag(Flag::N), *subtract);
+    }
+}
+
+// Verify RETI returns and enables interrupts straight away
+#[test]
+fn test_reti() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    cpu.load_test_ram(&[Instruction::Reti().as_byte()]);
+    cpu.set_sp(0x1000);
+    cpu.push(0x1234);
+    cpu.set_ime(false);
+
+    assert_eq!(cpu.step(), 4);
+    assert_eq!(cpu.pc, 0x1234);
+    assert_eq!(cpu.get_sp(), 0x1000);
+    assert!(cpu.ime());
+}
+
+// Verify HALT wakes on a pending interrupt with IME off, without dispatching
+#[test]
+fn test_halt_wake_ime_off() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    cpu.load_test_ram(&[
+        Instruction::Halt().as_byte(),
+        Instruction::Inc16(Register16Bit::BC).as_byte(),
+    ]);
+    cpu.set_ime(false);
+    cpu.write_byte(IE_ADDR, 1 << (Interrupt::TIMER as u8));
+
+    cpu.step();
+    assert!(cpu.halted());
+
+    // requested but not enabled doesn't wake
+    cpu.write_byte(IF_ADDR, 1 << (Interrupt::VBLANK as u8));
+    assert_eq!(cpu.step(), 1);
+    assert!(cpu.halted());
+
+    cpu.write_byte(IF_ADDR, 1 << (Interrupt::TIMER as u8));
+    assert_eq!(cpu.step(), 2);
+    assert!(!cpu.halted());
+    assert_eq!(cpu.pc, 2);
+    assert_eq!(cpu.get_reg_16(Register16Bit::BC), 1);
+    assert_eq!(cpu.read_byte(IF_ADDR), 1 << (Interrupt::TIMER as u8));
+}
+
+// Verify HALT with IME on wakes and dispatches the interrupt
+#[test]
+fn test_halt_dispatch() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    cpu.load_test_ram(&[Instruction::Halt().as_byte()]);
+    cpu.set_sp(0x1000);
+    cpu.set_ime(true);
+    cpu.write_byte(IE_ADDR, 0x1f);
+
+    cpu.step();
+    assert!(cpu.halted());
+
+    cpu.write_byte(IF_ADDR, 1 << (Interrupt::STAT as u8));
+    assert_eq!(cpu.step(), 5);
+    assert!(!cpu.halted());
+    assert!(!cpu.ime());
+    assert_eq!(cpu.pc, Interrupt::STAT.vector());
+    assert_eq!(cpu.read_word(cpu.get_sp()), 1);
+    assert_eq!(cpu.read_byte(IF_ADDR), 0);
+}
+
+// Verify the byte after HALT is executed twice when HALT exits immediately
+#[test]
+fn test_halt_bug() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    cpu.load_test_ram(&[
+        Instruction::Halt().as_byte(),
+        Instruction::LdImm(Register8Bit::A).as_byte(),
+        Instruction::Inc16(Register16Bit::DE).as_byte(),
+    ]);
+    cpu.set_ime(false);
+    cpu.write_byte(IE_ADDR, 1 << (Interrupt::JOYPAD as u8));
+    cpu.write_byte(IF_ADDR, 1 << (Interrupt::JOYPAD as u8));
+
+    cpu.step();
+    assert!(!cpu.halted());
+    assert_eq!(cpu.pc, 1);
+
+    // LD A,d8 reads its own opcode as the operand
+    cpu.step();
+    assert_eq!(cpu.get_reg(Register8Bit::A), Instruction::LdImm(Register8Bit::A).as_byte());
+    assert_eq!(cpu.pc, 2);
+
+    // and the real operand is then executed as an opcode
+    cpu.step();
+    assert_eq!(cpu.get_reg_16(Register16Bit::DE), 1);
+    assert_eq!(cpu.pc, 3);
+}
+
+// Verify pending interrupts are dispatched in priority order
+#[test]
+fn test_interrupt_priority() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    cpu.set_sp(0x1000);
+    cpu.write_byte(IE_ADDR, 0x1c);
+    cpu.write_byte(IF_ADDR, 0x1f);
+
+    for interrupt in [Interrupt::TIMER, Interrupt::SERIAL, Interrupt::JOYPAD].iter() {
+        cpu.set_ime(true);
+        assert_eq!(cpu.step(), 5);
+        assert_eq!(cpu.pc, interrupt.vector());
+        assert_eq!(cpu.read_byte(IF_ADDR) & (1 << (*interrupt as u8)), 0);
+    }
+
+    // VBLANK and STAT are requested but not enabled
+    assert_eq!(cpu.read_byte(IF_ADDR), 0x03);
+}
+
+// Verify EI only enables interrupts after the following instruction
+#[test]
+fn test_ei_delay() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    cpu.load_test_ram(&[
+        Instruction::Ei().as_byte(),
+        Instruction::Noop().as_byte(),
+        Instruction::Noop().as_byte(),
+    ]);
+    cpu.set_sp(0x1000);
+    cpu.write_byte(IE_ADDR, 0x01);
+    cpu.write_byte(IF_ADDR, 0x01);
+
+    assert_eq!(cpu.step(), 1);
+    assert!(!cpu.ime());
+    assert_eq!(cpu.step(), 1);
+    assert_eq!(cpu.pc, 2);
+
+    assert_eq!(cpu.step(), 5);
+    assert_eq!(cpu.pc, Interrupt::VBLANK.vector());
+    assert_eq!(cpu.read_word(cpu.get_sp()), 2);
+}
+
+// Verify DI straight after EI keeps interrupts disabled
+#[test]
+fn test_ei_di() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    cpu.load_test_ram(&[
+        Instruction::Ei().as_byte(),
+        Instruction::Di().as_byte(),
+        Instruction::Noop().as_byte(),
+    ]);
+    cpu.write_byte(IE_ADDR, 0x01);
+    cpu.write_byte(IF_ADDR, 0x01);
+
+    for _ in 0..3 {
+        assert_eq!(cpu.step(), 1);
+    }
+    assert_eq!(cpu.pc, 3);
+    assert!(!cpu.ime());
+}
+
+// Verify EI; HALT with an interrupt pending services it and returns to HALT
+#[test]
+fn test_ei_halt() {
+    let mut cpu = Cpu::new(FlatRam::new());
+    cpu.load_test_ram(&[
+        Instruction::Ei().as_byte(),
+        Instruction::Halt().as_byte(),
+    ]);
+    cpu.set_sp(0x1000);
+    cpu.write_byte(IE_ADDR, 0x04);
+    cpu.write_byte(IF_ADDR, 0x04);
+
+    cpu.step();
+    cpu.step();
+    assert!(!cpu.halted());
+
+    assert_eq!(cpu.step(), 5);
+    assert_eq!(cpu.pc, Interrupt::TIMER.vector());
+    assert_eq!(cpu.read_word(cpu.get_sp()), 1);
+}
+
+// Verify the PC high byte push landing on IE can change or cancel dispatch
+#[test]
+fn test_dispatch_ie_push() {
+    let mut cpu = Cpu::new(FlatRam::new());
+
+    // high byte 0x02 leaves only STAT enabled, so it wins over VBLANK
+    cpu.pc = 0x0200;
+    cpu.set_sp(0x0000);
+    cpu.set_ime(true);
+    cpu.write_byte(IE_ADDR, 0x03);
+    cpu.write_byte(IF_ADDR, 0x03);
+    assert_eq!(cpu.step(), 5);
+    assert_eq!(cpu.pc, Interrupt::STAT.vector());
+    assert_eq!(cpu.read_byte(IF_ADDR), 0x01);
+    assert_eq!(cpu.read_byte(IE_ADDR), 0x02);
+    assert_eq!(cpu.read_byte(0xfffe), 0x00);
+    assert_eq!(cpu.get_sp(), 0xfffe);
+
+    // high byte 0x02 disables VBLANK with nothing else pending, so the
+    // dispatch is cancelled and jumps to 0x0000
+    cpu.pc = 0x0200;
+    cpu.set_sp(0x0000);
+    cpu.set_ime(true);
+    cpu.write_byte(IE_ADDR, 0x01);
+    cpu.write_byte(IF_ADDR, 0x01);
+    assert_eq!(cpu.step(), 5);
+    assert_eq!(cpu.pc, 0x0000);
+    assert_eq!(cpu.read_byte(IF_ADDR), 0x01);
+    assert!(!cpu.ime());
+}
+
+// Verify the CPU runs against the real MMU, with EI/DI reaching the
+// interrupt controller
+#[test]
+fn test_mmu_bus() {
+    let mut cpu = Cpu::new(Mmu::new());
+
+    let program = [
+        Instruction::Ei().as_byte(),
+        Instruction::Noop().as_byte(),
+        Instruction::Di().as_byte(),
+    ];
+    for (i, byte) in program.iter().enumerate() {
+        cpu.bus_mut().mem_write_byte(0xc000 + i as u16, *byte);
+    }
+    cpu.pc = 0xc000;
+
+    cpu.step();
+    cpu.step();
+    assert!(cpu.bus().intc.get_ime());
+    assert_eq!(cpu.pc, 0xc002);
+
+    cpu.step();
+    assert!(!cpu.bus().intc.get_ime());
+    assert_eq!(cpu.read_byte(0xe000), Instruction::Ei().as_byte());
 }
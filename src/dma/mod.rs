@@ -0,0 +1,77 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use crate::memory::Memory;
+
+const OAM_DMA_LEN: usize = 160;
+
+/*
+ * OAM DMA
+ *
+ * Writing XX to 0xFF46 copies XX00..XX9F into OAM, one byte per M-cycle.
+ * The DMA controller only tracks progress, the actual copy is done by the
+ * MMU since the source can be anywhere on the bus.
+ */
+pub struct Dma {
+    reg: u8,
+    offset: Option<usize>,
+}
+
+impl Memory for Dma {
+    fn mem_read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0xff46 => self.reg,
+            _ => panic!("read from invalid address: {:#06x}", addr),
+        }
+    }
+
+    fn mem_write_byte(&mut self, addr: u16, val: u8) {
+        match addr {
+            0xff46 => {
+                self.reg = val;
+                self.offset = Some(0);
+            },
+            _ => panic!("write to invalid address: {:#06x}", addr),
+        }
+    }
+}
+
+impl Default for Dma {
+    fn default() -> Dma {
+        Dma::new()
+    }
+}
+
+impl Dma {
+    pub fn new() -> Dma {
+        Dma {
+            reg: 0xff,
+            offset: None,
+        }
+    }
+
+    // advance the transfer by one M-cycle, returning the (source address,
+    // OAM offset) of the byte to copy, if any
+    pub fn next_transfer(&mut self) -> Option<(u16, usize)> {
+        let offset = self.offset?;
+        let mut src = ((self.reg as u16) << 8) + (offset as u16);
+
+        // sources above 0xDFFF are routed to WRAM
+        if src >= 0xe000 {
+            src -= 0x2000;
+        }
+
+        self.offset = if offset + 1 < OAM_DMA_LEN { Some(offset + 1) } else { None };
+        Some((src, offset))
+    }
+}
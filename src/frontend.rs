@@ -0,0 +1,199 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use std::io;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use sdl2::Sdl;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::WindowCanvas;
+
+use dookieboy::{Button, Gameboy, DOTS_PER_FRAME, DOTS_PER_SECOND};
+use dookieboy::{PALETTE, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// how often battery RAM is flushed to disk if it has changed, in frames
+const AUTOSAVE_FRAMES: usize = 60 * 10;
+
+// SDL window, keyboard and wall clock pacing for a Gameboy
+pub struct Frontend {
+    sdl_context: Sdl,
+    canvas: WindowCanvas,
+    speed: f64,
+    fast_forward: bool,
+}
+
+impl Frontend {
+    pub fn new(width: u32, height: u32) -> Result<Frontend, String> {
+        let context = sdl2::init()?;
+        let video_subsystem = context.video()?;
+        let window = video_subsystem
+            .window("DookieBoy", width, height)
+            .position_centered()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let sdl_canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+
+        Ok(Frontend {
+            sdl_context: context,
+            canvas: sdl_canvas,
+            speed: 1.0,
+            fast_forward: false,
+        })
+    }
+
+    // emulation speed multiplier used when pacing frames, 1.0 is real time
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+    }
+
+    pub fn run(&mut self, gb: &mut Gameboy) -> Result<(), io::Error> {
+        let mut next_frame = Instant::now();
+        let mut frames_since_save = 0;
+        let mut result = Ok(());
+
+        while self.handle_sdl2_events(gb) {
+            gb.run_frame();
+
+            if gb.check_and_consume_frame() {
+                if let Err(e) = self.draw_frame(gb.framebuffer()) {
+                    result = Err(io::Error::other(e));
+                    break;
+                }
+            }
+
+            frames_since_save += 1;
+            if frames_since_save >= AUTOSAVE_FRAMES {
+                frames_since_save = 0;
+                gb.save()?;
+            }
+
+            // sync to wall clock, unless fast forwarding. if we've fallen
+            // behind don't try to catch up, just start pacing from now
+            let now = Instant::now();
+            if self.fast_forward {
+                next_frame = now;
+            } else {
+                next_frame += self.frame_duration();
+                if next_frame > now {
+                    sleep(next_frame - now);
+                } else {
+                    next_frame = now;
+                }
+            }
+        }
+
+        // battery RAM is flushed however the loop ended, a draw error is
+        // reported over a failed save
+        let saved = gb.save();
+        result.and(saved)
+    }
+
+    fn frame_duration(&self) -> Duration {
+        Duration::from_secs_f64((DOTS_PER_FRAME as f64) / DOTS_PER_SECOND / self.speed)
+    }
+
+    fn draw_frame(&mut self, framebuffer: &[u8]) -> Result<(), String> {
+        let texture_creator = self.canvas.texture_creator();
+        let mut texture = texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGB24,
+                                      SCREEN_WIDTH as u32,
+                                      SCREEN_HEIGHT as u32)
+            .map_err(|e| e.to_string())?;
+
+        texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
+            for y in 0..SCREEN_HEIGHT {
+                for x in 0..SCREEN_WIDTH {
+                    let shade = framebuffer[y * SCREEN_WIDTH + x] as usize;
+                    let offset = y * pitch + x * 3;
+                    buffer[offset..offset + 3].copy_from_slice(&PALETTE[shade]);
+                }
+            }
+        })?;
+
+        self.canvas.copy(&texture, None, None)?;
+        self.canvas.present();
+
+        Ok(())
+    }
+
+    // returns false once the user has asked to quit
+    fn handle_sdl2_events(&mut self, gb: &mut Gameboy) -> bool {
+        let mut pump = self.sdl_context.event_pump().unwrap();
+        for event in pump.poll_iter() {
+            match event {
+                Event::Quit {..}
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    repeat: false,
+                    ..
+                } => return false,
+                Event::KeyDown { keycode: Some(Keycode::Space), repeat: false, .. } => {
+                    self.fast_forward = !self.fast_forward;
+                },
+                Event::KeyDown { keycode: Some(Keycode::A), repeat: false, .. } => {
+                    gb.update_button(Button::LEFT, true);
+                },
+                Event::KeyDown { keycode: Some(Keycode::D), repeat: false, .. } => {
+                    gb.update_button(Button::RIGHT, true);
+                },
+                Event::KeyDown { keycode: Some(Keycode::W), repeat: false, .. } => {
+                    gb.update_button(Button::UP, true);
+                },
+                Event::KeyDown { keycode: Some(Keycode::S), repeat: false, .. } => {
+                    gb.update_button(Button::DOWN, true);
+                },
+                Event::KeyDown { keycode: Some(Keycode::J), repeat: false, .. } => {
+                    gb.update_button(Button::A, true);
+                },
+                Event::KeyDown { keycode: Some(Keycode::K), repeat: false, .. } => {
+                    gb.update_button(Button::B, true);
+                },
+                Event::KeyDown { keycode: Some(Keycode::Return), repeat: false, .. } => {
+                    gb.update_button(Button::START, true);
+                },
+                Event::KeyDown { keycode: Some(Keycode::RShift), repeat: false, .. } => {
+                    gb.update_button(Button::SELECT, true);
+                },
+                Event::KeyUp { keycode: Some(Keycode::A), repeat: false, .. } => {
+                    gb.update_button(Button::LEFT, false);
+                },
+                Event::KeyUp { keycode: Some(Keycode::D), repeat: false, .. } => {
+                    gb.update_button(Button::RIGHT, false);
+                },
+                Event::KeyUp { keycode: Some(Keycode::W), repeat: false, .. } => {
+                    gb.update_button(Button::UP, false);
+                },
+                Event::KeyUp { keycode: Some(Keycode::S), repeat: false, .. } => {
+                    gb.update_button(Button::DOWN, false);
+                },
+                Event::KeyUp { keycode: Some(Keycode::J), repeat: false, .. } => {
+                    gb.update_button(Button::A, false);
+                },
+                Event::KeyUp { keycode: Some(Keycode::K), repeat: false, .. } => {
+                    gb.update_button(Button::B, false);
+                },
+                Event::KeyUp { keycode: Some(Keycode::Return), repeat: false, .. } => {
+                    gb.update_button(Button::START, false);
+                },
+                Event::KeyUp { keycode: Some(Keycode::RShift), repeat: false, .. } => {
+                    gb.update_button(Button::SELECT, false);
+                },
+                _ => continue,
+            }
+        }
+        true
+    }
+}
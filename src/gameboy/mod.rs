@@ -11,217 +11,286 @@
 // ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
 // ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
 
-use std::cell::RefCell;
-use std::io;
-use std::rc::Rc;
-use std::time::Duration;
-use std::thread::sleep;
-
-use sdl2::Sdl;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::render::WindowCanvas;
-
-use crate::cpu::Cpu;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cartridge::{CartridgeError, Clock, Rumble};
+use crate::cpu::{Cpu, Register8Bit};
 use crate::intc::Interrupt;
 use crate::int_src::InterruptSource;
 use crate::mmu::Mmu;
 use crate::joypad::Button;
 use crate::memory::Memory;
+use crate::serial::link::LinkCable;
+
+// frames are timed in normal speed T-cycles (PPU dots), since the CPU
+// runs twice as many M-cycles per frame in CGB double speed mode
+pub const DOTS_PER_FRAME: usize = 70224;
+pub const DOTS_PER_SECOND: f64 = 4194304.0;
 
+/*
+ * Gameboy
+ *
+ * The whole machine. The CPU owns the MMU, which in turn owns every
+ * peripheral, so there is no shared state and a Gameboy can be moved to
+ * (and run on) another thread. Displaying frames, pacing and input are
+ * left to whoever is driving it.
+ */
 pub struct Gameboy {
-    cpu: Cpu,
-    mmu: Rc<RefCell<Mmu>>,
+    cpu: Cpu<Mmu>,
+
+    frame_dots: usize,
 
-    sdl_context: Option<Sdl>,
-    canvas: Option<WindowCanvas>,
-    width: u32,
-    height: u32,
+    save_path: Option<PathBuf>,
 }
 
 impl Memory for Gameboy {
     fn mem_read_byte(&self, addr: u16) -> u8 {
-        self.mmu.borrow_mut().mem_read_byte(addr)
+        self.mmu().mem_read_byte(addr)
     }
 
     fn mem_write_byte(&mut self, addr: u16, val: u8) {
-        self.mmu.borrow_mut().mem_write_byte(addr, val);
+        self.mmu_mut().mem_write_byte(addr, val);
     }
 }
 
-impl Gameboy {
-    pub fn new(width: u32, height: u32) -> Gameboy {
-        let mmu = Rc::new(RefCell::new(Mmu::new()));
+impl Default for Gameboy {
+    fn default() -> Gameboy {
+        Gameboy::new()
+    }
+}
 
+impl Gameboy {
+    pub fn new() -> Gameboy {
         Gameboy {
-            cpu: Cpu::new(Rc::clone(&mmu)),
-            mmu: Rc::clone(&mmu),
-            sdl_context: None,
-            canvas: None,
-            width: width,
-            height: height,
+            cpu: Cpu::new(Mmu::new()),
+            frame_dots: 0,
+            save_path: None,
         }
     }
 
-    pub fn cpu(&mut self) -> &mut Cpu {
-        &mut self.cpu
+    // debugging hooks for test ROMs and the shell. the CPU itself stays
+    // private so the bus type doesn't leak into the API
+    pub fn reg(&self, reg: Register8Bit) -> u8 {
+        self.cpu.get_reg(reg)
     }
 
-    pub fn init_sdl(&mut self) -> Result<(), String> {
-        let context = sdl2::init()?;
-        let video_subsystem = context.video()?;
-        let window = video_subsystem
-            .window("DookieBoy", self.width, self.height)
-            .position_centered()
-            .build()
-            .map_err(|e| e.to_string())?;
-        let sdl_canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+    // stop at LD B,B, the software breakpoint used by mooneye test ROMs
+    pub fn set_ld_b_b_breakpoint(&mut self, enabled: bool) {
+        self.cpu.set_ld_b_b_breakpoint(enabled);
+    }
 
-        self.sdl_context = Some(context);
-        self.canvas = Some(sdl_canvas);
+    pub fn check_and_consume_breakpoint(&mut self) -> bool {
+        self.cpu.check_and_consume_breakpoint()
+    }
 
-        Ok(())
+    // registers and flags, formatted for a debug dump
+    pub fn cpu_state(&self) -> String {
+        self.cpu.to_string()
+    }
+
+    fn mmu(&self) -> &Mmu {
+        self.cpu.bus()
+    }
+
+    fn mmu_mut(&mut self) -> &mut Mmu {
+        self.cpu.bus_mut()
+    }
+
+    // called whenever a rumble cartridge turns its motor on or off
+    pub fn set_rumble_handler(&mut self, handler: Box<dyn Rumble>) {
+        self.mmu_mut().cartridge.set_rumble_handler(handler);
+    }
+
+    // wall clock for cartridges with an RTC, the host's by default. set
+    // this before loading a ROM
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.mmu_mut().cartridge.set_clock(clock);
+    }
+
+    // plug something into the link port, nothing is connected by default
+    pub fn set_link_cable(&mut self, cable: Box<dyn LinkCable>) {
+        self.mmu_mut().serial.set_link_cable(cable);
     }
 
-    pub fn load_rom(&mut self, path: String) -> Result<(), io::Error> {
-        self.mmu.borrow_mut().cartridge.load_rom(path)?;
+    // copy everything sent over the serial port to the given writer, e.g.
+    // stdout for test ROMs and homebrew which print over serial
+    pub fn set_serial_tee(&mut self, tee: Box<dyn Write + Send>) {
+        self.mmu_mut().serial.set_tee(Some(tee));
+    }
+
+    // warn about, rather than reject, ROMs with a bad global checksum or logo
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.mmu_mut().cartridge.set_lenient(lenient);
+    }
+
+    // problems lenient mode let through when the last ROM was loaded
+    pub fn rom_warnings(&self) -> &[CartridgeError] {
+        self.mmu().cartridge.warnings()
+    }
+
+    // load a ROM, along with its .sav file if the cartridge has a battery
+    pub fn load_rom(&mut self, path: String) -> Result<(), CartridgeError> {
+        let save_path = Path::new(&path).with_extension("sav");
+        let data = fs::read(&path)?;
+        self.load_rom_data(data)?;
+
+        let cartridge = &mut self.mmu_mut().cartridge;
+        if cartridge.has_battery() {
+            cartridge.load_save(&save_path)?;
+            self.save_path = Some(save_path);
+        }
 
         Ok(())
     }
 
-    pub fn reset(&mut self) {
-        self.cpu.reset();
+    // load a ROM image that's already in memory. there's no .sav file to
+    // go with it, so battery RAM starts out blank and save() does nothing
+    pub fn load_rom_data(&mut self, data: Vec<u8>) -> Result<(), CartridgeError> {
+        let mmu = self.cpu.bus_mut();
+
+        mmu.cartridge.load_rom_data(data)?;
+        let cgb = mmu.cartridge.is_cgb();
+        mmu.set_cgb(cgb);
+        self.save_path = None;
+
+        Ok(())
     }
 
-    pub fn run(&mut self) -> Result<(), io::Error> {
-
-        // TODO: handle stop and halt
-        //  If both the interrupt request flag and the corresponding interrupt enable flag are set,
-        //  HALT mode is exited, even if the interrupt master enable flag is not set.
-        //
-        //  If the interrupt master enable flag is set, the contents of the program coounter are
-        //  pushed to the stack and control jumps to the starting address of the interrupt.
-        loop {
-            self.handle_sdl2_events()?;
-            self.check_for_interrupts();
-            self.handle_interrupts();
-
-            if !self.cpu.halted() {
-                self.cpu.step();
-            } else {
-                // just sleep for 10ms now until we decide what
-                // to do
-                sleep(Duration::from_millis(10));
+    // write battery RAM to the .sav file if anything has changed since the
+    // last save
+    pub fn save(&mut self) -> Result<(), io::Error> {
+        if let Some(path) = &self.save_path {
+            let cartridge = &mut self.cpu.bus_mut().cartridge;
+            if cartridge.is_ram_dirty() {
+                cartridge.write_save(path)?;
             }
         }
+
+        Ok(())
     }
 
-    fn check_for_interrupts(&mut self) {
-        let mmu = &mut self.mmu.borrow_mut();
+    // keep everything sent over the serial port for take_serial_output
+    pub fn capture_serial_output(&mut self, capture: bool) {
+        self.mmu_mut().serial.capture_output(capture);
+    }
 
-        if mmu.joypad.check_and_consume_int_req() {
-            mmu.intc.request(Interrupt::JOYPAD);
-        }
+    // bytes sent over the serial port since the last call, if capturing
+    pub fn take_serial_output(&mut self) -> Vec<u8> {
+        self.mmu_mut().serial.take_output()
     }
 
-    fn handle_interrupts(&mut self) {
-        for interrupt in Interrupt::iterator() {
-            let should_trigger: bool;
-            let ime: bool;
+    // the last frame drawn, one DMG shade (0-3) per pixel
+    pub fn framebuffer(&self) -> &[u8] {
+        self.mmu().ppu.framebuffer()
+    }
+
+    // whether a new frame has been drawn since the last call
+    pub fn check_and_consume_frame(&mut self) -> bool {
+        self.mmu_mut().ppu.check_and_consume_frame()
+    }
+
+    pub fn update_button(&mut self, button: Button, pressed: bool) {
+        self.mmu_mut().joypad.update_button(button, pressed);
+    }
+
+    // whether the CPU has hung on an illegal opcode
+    pub fn locked(&self) -> bool {
+        self.cpu.locked()
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+    }
 
-            //TODO: find out how to borrow both
-            {
-                let intc = &mut self.mmu.borrow_mut().intc;
-                should_trigger = intc.should_trigger(interrupt);
-                ime = intc.get_ime();
+    // run the system for exactly one frame's worth of cycles. any cycles
+    // the last instruction ran over by are taken out of the next frame
+    pub fn run_frame(&mut self) {
+        while self.frame_dots < DOTS_PER_FRAME {
+            let cycles = self.step();
+            self.frame_dots += self.cycles_to_dots(cycles);
+        }
 
-                if should_trigger && ime {
-                    intc.set_ime(false);
-                    intc.clear_request(interrupt);
-                }
+        self.frame_dots -= DOTS_PER_FRAME;
+    }
+
+    // execute a single CPU instruction (or interrupt dispatch) and advance
+    // the rest of the system by the same amount, returning the M-cycles used
+    pub fn step(&mut self) -> usize {
+        // STOP stops the system clock, so nothing runs until a button
+        // press pulls one of the selected joypad lines low
+        if self.cpu.stopped() {
+            if !self.mmu().joypad.any_line_low() {
+                return 1;
             }
+            self.cpu.exit_stop();
+        }
 
-            if should_trigger && ime {
-                self.cpu.trigger_interrupt(interrupt);
+        let cycles = self.cpu.step();
 
-                // TODO: emulate halt bug
-                // https://gbdev.io/pandocs/halt.html#halt-bug
-                if self.cpu.halted() {
-                    self.cpu.exit_halt();
-                }
+        if self.cpu.stopped() {
+            self.mmu_mut().timer.reset_div();
 
-                // break from loop, we only handle one interrupt at
-                // a time
-                break;
+            // an armed speed switch takes the place of low power mode
+            if self.mmu_mut().speed_switch() {
+                self.cpu.exit_stop();
             }
         }
+
+        self.tick(cycles);
+        cycles
     }
 
-    fn handle_sdl2_events(&mut self) -> Result<(), io::Error> {
-        if let Some(context) = &self.sdl_context {
-            let mut pump = context.event_pump().unwrap();
-            let joypad = &mut self.mmu.borrow_mut().joypad;
-            for event in pump.poll_iter() {
-                match event {
-                    Event::Quit {..}
-                    | Event::KeyDown {
-                        keycode: Some(Keycode::Escape),
-                        repeat: false,
-                        ..
-                    } => return Err(io::Error::from_raw_os_error(0)),
-                    Event::KeyDown { keycode: Some(Keycode::A), repeat: false, .. } => {
-                        joypad.update_button(Button::LEFT, true);
-                    },
-                    Event::KeyDown { keycode: Some(Keycode::D), repeat: false, .. } => {
-                        joypad.update_button(Button::RIGHT, true);
-                    },
-                    Event::KeyDown { keycode: Some(Keycode::W), repeat: false, .. } => {
-                        joypad.update_button(Button::UP, true);
-                    },
-                    Event::KeyDown { keycode: Some(Keycode::S), repeat: false, .. } => {
-                        joypad.update_button(Button::DOWN, true);
-                    },
-                    Event::KeyDown { keycode: Some(Keycode::J), repeat: false, .. } => {
-                        joypad.update_button(Button::A, true);
-                    },
-                    Event::KeyDown { keycode: Some(Keycode::K), repeat: false, .. } => {
-                        joypad.update_button(Button::B, true);
-                    },
-                    Event::KeyDown { keycode: Some(Keycode::Return), repeat: false, .. } => {
-                        joypad.update_button(Button::START, true);
-                    },
-                    Event::KeyDown { keycode: Some(Keycode::RShift), repeat: false, .. } => {
-                        joypad.update_button(Button::SELECT, true);
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::A), repeat: false, .. } => {
-                        joypad.update_button(Button::LEFT, false);
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::D), repeat: false, .. } => {
-                        joypad.update_button(Button::RIGHT, false);
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::W), repeat: false, .. } => {
-                        joypad.update_button(Button::UP, false);
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::S), repeat: false, .. } => {
-                        joypad.update_button(Button::DOWN, false);
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::J), repeat: false, .. } => {
-                        joypad.update_button(Button::A, false);
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::K), repeat: false, .. } => {
-                        joypad.update_button(Button::B, false);
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::Return), repeat: false, .. } => {
-                        joypad.update_button(Button::START, false);
-                    },
-                    Event::KeyUp { keycode: Some(Keycode::RShift), repeat: false, .. } => {
-                        joypad.update_button(Button::SELECT, false);
-                    },
-                    _ => continue,
-                }
-            }
+    // in double speed mode the CPU, timer, serial and DMA run twice as
+    // fast but the PPU doesn't, so it only gets 2 dots per M-cycle
+    fn cycles_to_dots(&self, cycles: usize) -> usize {
+        if self.mmu().double_speed() {
+            cycles * 2
+        } else {
+            cycles * 4
         }
-        Ok(())
     }
-}
 
+    // advance every peripheral by the given number of M-cycles so the
+    // whole machine stays in lockstep with the CPU
+    pub fn tick(&mut self, cycles: usize) {
+        let dots = self.cycles_to_dots(cycles);
+
+        {
+            let mmu = self.mmu_mut();
+            let t_cycles = cycles * 4;
+
+            mmu.timer.step(t_cycles);
+            mmu.serial.step(t_cycles);
+            mmu.step_dma(cycles);
+            mmu.ppu.step(dots);
+        }
+
+        self.check_for_interrupts();
+    }
+
+    fn check_for_interrupts(&mut self) {
+        let mmu = self.mmu_mut();
+
+        if mmu.joypad.check_and_consume_int_req() {
+            mmu.intc.request(Interrupt::JOYPAD);
+        }
+
+        if mmu.timer.check_and_consume_int_req() {
+            mmu.intc.request(Interrupt::TIMER);
+        }
+
+        if mmu.ppu.vblank_int.check_and_consume_int_req() {
+            mmu.intc.request(Interrupt::VBLANK);
+        }
+
+        if mmu.ppu.stat_int.check_and_consume_int_req() {
+            mmu.intc.request(Interrupt::STAT);
+        }
+
+        if mmu.serial.check_and_consume_int_req() {
+            mmu.intc.request(Interrupt::SERIAL);
+        }
+    }
+}
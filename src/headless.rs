@@ -0,0 +1,42 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use dookieboy::Gameboy;
+use dookieboy::{PALETTE, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// run flat out for the given number of frames with no window, then dump
+// whatever is on screen to path as a binary PPM. the game is never saved,
+// so unattended runs leave the ROM's .sav alone
+pub fn run(gameboy: &mut Gameboy, frames: usize, path: &Path) -> Result<(), io::Error> {
+    for _ in 0..frames {
+        gameboy.run_frame();
+    }
+
+    write_ppm(path, gameboy.framebuffer())
+}
+
+fn write_ppm(path: &Path, framebuffer: &[u8]) -> Result<(), io::Error> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    write!(out, "P6\n{} {}\n255\n", SCREEN_WIDTH, SCREEN_HEIGHT)?;
+    for shade in framebuffer {
+        out.write_all(&PALETTE[*shade as usize])?;
+    }
+
+    out.flush()
+}
@@ -30,3 +30,38 @@ pub trait InterruptSource {
     // consumes a pending interrupt
     fn consume_int_req(&mut self);
 }
+
+// A single interrupt request line. Peripherals which drive more than one
+// interrupt keep one of these per line instead of implementing
+// InterruptSource themselves
+pub struct IntReq {
+    req: bool,
+}
+
+impl InterruptSource for IntReq {
+    fn check_int_req(&self) -> bool {
+        self.req
+    }
+
+    fn consume_int_req(&mut self) {
+        self.req = false;
+    }
+}
+
+impl Default for IntReq {
+    fn default() -> IntReq {
+        IntReq::new()
+    }
+}
+
+impl IntReq {
+    pub fn new() -> IntReq {
+        IntReq {
+            req: false,
+        }
+    }
+
+    pub fn raise(&mut self) {
+        self.req = true;
+    }
+}
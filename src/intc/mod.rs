@@ -83,19 +83,4 @@ impl InterruptController {
     pub fn request(&mut self, interrupt: Interrupt) {
         self.flag |= 1 << (interrupt as u8);
     }
-
-    pub fn clear_request(&mut self, interrupt: Interrupt) {
-        self.flag &= !(1 << (interrupt as u8));
-    }
-
-    pub fn should_trigger(&self, interrupt: Interrupt) -> bool {
-        self.is_enabled(interrupt) && self.is_requested(interrupt)
-    }
-
-    pub fn is_enabled(&self, interrupt: Interrupt) -> bool {
-        ((self.enable >> (interrupt as u8)) & 1) == 1
-    }
-    pub fn is_requested(&self, interrupt: Interrupt) -> bool {
-        (self.flag >> (interrupt as u8)) & 1 == 1
-    }
 }
@@ -97,6 +97,13 @@ impl Joypad {
         self.buttons[idx] = state;
     }
 
+    // true if any selected button is pressed, pulling its P1 line low.
+    // this is what brings the CPU out of STOP
+    pub fn any_line_low(&self) -> bool {
+        (self.direction_select && self.buttons[Button::RIGHT as usize..=Button::DOWN as usize].contains(&true))
+            || (self.action_select && self.buttons[Button::A as usize..=Button::START as usize].contains(&true))
+    }
+
     #[cfg(test)]
     fn update_dir_select(&mut self, state: bool) {
         self.direction_select = state;
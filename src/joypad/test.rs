@@ -48,3 +48,23 @@ fn test_joypad_reg() {
     assert_eq!(reg, 0x10,
                "action: reported {:#04x} instead of {:#04x}", reg, 0x10);
 }
+
+// Verify only selected buttons pull a line low
+#[test]
+fn test_any_line_low() {
+    let mut jp = Joypad::new();
+    assert!(!jp.any_line_low());
+
+    jp.update_button(Button::START, true);
+    jp.update_dir_select(true);
+    jp.update_act_select(false);
+    assert!(!jp.any_line_low());
+
+    jp.update_act_select(true);
+    assert!(jp.any_line_low());
+
+    jp.update_button(Button::START, false);
+    jp.update_button(Button::LEFT, true);
+    jp.update_act_select(false);
+    assert!(jp.any_line_low());
+}
@@ -0,0 +1,57 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+/*
+ * DookieBoy
+ *
+ * The emulator core, with no display or audio of its own. Everything most
+ * frontends need is on Gameboy:
+ *
+ *   load_rom / load_rom_data   cartridge from a file (plus .sav) or bytes
+ *   reset                      start from the post boot ROM state
+ *   step / run_frame           one instruction, or one frame of 70224 dots
+ *   framebuffer                SCREEN_WIDTH x SCREEN_HEIGHT shades (0-3)
+ *   update_button              press or release a Button
+ *   mem_read_byte / ...        the CPU's view of memory, via Memory
+ *   set_link_cable             connect a LinkCable, e.g. a PeerLink
+ *
+ * Only what is re-exported here is public, the modules behind it are free
+ * to change.
+ *
+ * A Gameboy is Send, so any number of them can be run on other threads.
+ * The SDL window lives in the dookieboy binary, behind the sdl feature.
+ */
+
+mod bus;
+mod cartridge;
+mod cpu;
+mod dma;
+mod gameboy;
+mod intc;
+mod int_src;
+mod joypad;
+mod memory;
+mod mmu;
+mod ppu;
+mod serial;
+mod timer;
+
+pub use crate::cartridge::{CartridgeError, Clock, Rumble};
+pub use crate::cpu::Register8Bit;
+pub use crate::gameboy::{Gameboy, DOTS_PER_FRAME, DOTS_PER_SECOND};
+pub use crate::joypad::Button;
+pub use crate::memory::Memory;
+pub use crate::ppu::{PALETTE, SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use crate::serial::link::LinkCable;
+pub use crate::serial::peer::PeerLink;
@@ -11,23 +11,31 @@
 // ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
 // ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
 
-mod cartridge;
-mod cpu;
-mod gameboy;
-mod intc;
-mod int_src;
-mod joypad;
-mod memory;
-mod mmu;
-mod serial;
+#[cfg(feature = "sdl")]
+mod frontend;
+mod headless;
 mod shell;
 
 use std::env;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-use crate::gameboy::Gameboy;
+use dookieboy::Gameboy;
+use dookieboy::PeerLink;
+
+#[cfg(feature = "sdl")]
+use crate::frontend::Frontend;
 use crate::shell::{Cmd, Shell};
 
+// speeds outside this range aren't useful and very small ones overflow
+// the frame duration
+const MIN_SPEED: f64 = 0.01;
+const MAX_SPEED: f64 = 100.0;
+
+#[cfg(feature = "sdl")]
 const WIDTH: u32 = 800;
+#[cfg(feature = "sdl")]
 const HEIGHT: u32 = 600;
 
 fn is_gb_rom(filename: &str) -> bool {
@@ -39,10 +47,70 @@ fn is_gb_rom(filename: &str) -> bool {
     false
 }
 
+// host:port is a TCP socket, anything else is a unix socket path
+fn open_link(addr: &str, listen: bool) -> io::Result<PeerLink> {
+    #[cfg(unix)]
+    if !addr.contains(':') {
+        return if listen { PeerLink::unix_listen(addr) } else { PeerLink::unix_connect(addr) };
+    }
+
+    if listen { PeerLink::tcp_listen(addr) } else { PeerLink::tcp_connect(addr) }
+}
+
+// "-" is stdout, anything else a file to create
+fn open_tee(path: &str) -> io::Result<Box<dyn Write + Send>> {
+    if path == "-" {
+        Ok(Box::new(io::stdout()))
+    } else {
+        Ok(Box::new(File::create(path)?))
+    }
+}
+
+#[cfg(feature = "sdl")]
+fn run_sdl(gameboy: &mut Gameboy, speed: f64) {
+    let mut frontend = match Frontend::new(WIDTH, HEIGHT) {
+        Ok(frontend) => frontend,
+        Err(_e) => {
+            println!("dookieboy couldn't initialize SDL :'(");
+            print_usage();
+            std::process::exit(1);
+        },
+    };
+    frontend.set_speed(speed);
+
+    match frontend.run(gameboy) {
+        Ok(_) => {},
+        Err(e) => {
+            println!("dookieboy encountered big error: {}\n", e);
+            print_usage();
+            std::process::exit(1);
+        },
+    }
+}
+
+#[cfg(not(feature = "sdl"))]
+fn run_sdl(_gameboy: &mut Gameboy, _speed: f64) {
+    println!("dookieboy was built without SDL, rebuild with --features sdl to get a window");
+    std::process::exit(1);
+}
+
 fn print_usage() {
-    println!("usage: dookieboy [-d] rom_path");
+    println!("usage: dookieboy [-d] [-l] [-s speed] [-p addr | -P addr] [-t path]");
+    println!("                 [--headless --frames n [--out path]] rom_path");
     println!("  rom_path: absolute or relative path to ROM file");
     println!("  -d:       enable debug shell");
+    println!("  -l:       lenient mode, load ROMs with bad global checksum or logo");
+    println!("  -s:       emulation speed multiplier from 0.01 to 100, default 1.0");
+    println!("            (space toggles fast forward while running)");
+    println!("  -p:       connect the link cable to another dookieboy listening on addr");
+    println!("  -P:       wait for another dookieboy to connect its link cable on addr");
+    println!("            addr is host:port for TCP, otherwise a unix socket path");
+    println!("  -t:       write everything sent over the serial port to path, - for stdout");
+    println!("  --headless:");
+    println!("            run without a window for --frames n frames as fast as possible,");
+    println!("            then write the screen to --out path as a PPM image (default is");
+    println!("            rom_path with a .ppm extension). exits 0 if the image was written.");
+    println!("            the .sav file is never written, and -d can't be used with it");
 }
 
 fn main() {
@@ -56,11 +124,17 @@ fn main() {
     // argument fields
     let mut debug: bool = false;
     let mut rom: String = String::new();
+    let mut speed: f64 = 1.0;
+    let mut lenient: bool = false;
+    let mut link: Option<(String, bool)> = None;
+    let mut tee: Option<String> = None;
+    let mut headless: bool = false;
+    let mut frames: Option<usize> = None;
+    let mut out: Option<PathBuf> = None;
 
-    for (i, arg) in args.iter().enumerate() {
-        if i == 0 {
-            continue;
-        }
+    let mut i = 1;
+    while i < num_args {
+        let arg = &args[i];
 
         if i == (num_args - 1) {
             if !is_gb_rom(arg.as_str()) {
@@ -75,21 +149,126 @@ fn main() {
         match arg.as_str() {
             #[cfg(debug_assertions)]
             "-d" => debug = true,
+            "-l" => lenient = true,
+            "-s" => {
+                i += 1;
+                match args.get(i).and_then(|s| s.parse::<f64>().ok()) {
+                    Some(s) if (MIN_SPEED..=MAX_SPEED).contains(&s) && i < (num_args - 1) => speed = s,
+                    _ => {
+                        println!("invalid speed multiplier");
+                        print_usage();
+                        std::process::exit(1);
+                    },
+                }
+            },
+            "-p" | "-P" => {
+                i += 1;
+                match args.get(i) {
+                    Some(addr) if i < (num_args - 1) => link = Some((addr.clone(), arg == "-P")),
+                    _ => {
+                        println!("link address not provided");
+                        print_usage();
+                        std::process::exit(1);
+                    },
+                }
+            },
+            "-t" => {
+                i += 1;
+                match args.get(i) {
+                    Some(path) if i < (num_args - 1) => tee = Some(path.clone()),
+                    _ => {
+                        println!("serial output path not provided");
+                        print_usage();
+                        std::process::exit(1);
+                    },
+                }
+            },
+            "--headless" => headless = true,
+            "--frames" => {
+                i += 1;
+                match args.get(i).and_then(|s| s.parse::<usize>().ok()) {
+                    Some(n) if i < (num_args - 1) => frames = Some(n),
+                    _ => {
+                        println!("invalid frame count");
+                        print_usage();
+                        std::process::exit(1);
+                    },
+                }
+            },
+            "--out" => {
+                i += 1;
+                match args.get(i) {
+                    Some(path) if i < (num_args - 1) => out = Some(PathBuf::from(path)),
+                    _ => {
+                        println!("output path not provided");
+                        print_usage();
+                        std::process::exit(1);
+                    },
+                }
+            },
             &_ => {},
         }
+
+        i += 1;
+    }
+
+    if headless && frames.is_none() {
+        println!("--headless needs a frame count");
+        print_usage();
+        std::process::exit(1);
     }
+    if headless && debug {
+        println!("--headless can't be used with -d");
+        print_usage();
+        std::process::exit(1);
+    }
+    if !headless && (frames.is_some() || out.is_some()) {
+        println!("--frames and --out only apply to --headless");
+        print_usage();
+        std::process::exit(1);
+    }
+    let out = out.unwrap_or_else(|| Path::new(&rom).with_extension("ppm"));
 
-    let mut gameboy = Gameboy::new(WIDTH, HEIGHT);
+    let mut gameboy = Gameboy::new();
+    gameboy.set_lenient(lenient);
     match gameboy.load_rom(rom) {
-        Ok(_) => {},
-        Err(_e) => {
-            println!("unable to load rom file");
+        Ok(_) => {
+            for warning in gameboy.rom_warnings() {
+                eprintln!("warning: {}", warning);
+            }
+        },
+        Err(e) => {
+            println!("unable to load rom file: {}", e);
             print_usage();
             std::process::exit(1);
         },
     }
     gameboy.reset();
 
+    if let Some(path) = tee {
+        match open_tee(&path) {
+            Ok(writer) => gameboy.set_serial_tee(writer),
+            Err(e) => {
+                println!("unable to open serial output file: {}", e);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    if let Some((addr, listen)) = link {
+        if listen {
+            println!("waiting for link cable on {}", addr);
+        }
+
+        match open_link(&addr, listen) {
+            Ok(cable) => gameboy.set_link_cable(Box::new(cable)),
+            Err(e) => {
+                println!("unable to connect link cable: {}", e);
+                std::process::exit(1);
+            },
+        }
+    }
+
     if debug {
         let mut last_cmd: Option<Cmd> = None;
         let mut cmd: Option<Cmd>;
@@ -104,23 +283,16 @@ fn main() {
                 last_cmd = cmd.take();
             }
         }
-    } else {
-        match gameboy.init_sdl() {
-            Ok(_) => {},
-            Err(_e) => {
-                println!("dookieboy couldn't initialize SDL :'(");
-                print_usage();
-                std::process::exit(1);
-            },
-        }
-
-        match gameboy.run() {
+    } else if headless {
+        // frame count was checked while parsing arguments
+        match headless::run(&mut gameboy, frames.unwrap(), &out) {
             Ok(_) => {},
             Err(e) => {
-                println!("dookieboy encountered big error: {}\n", e);
-                print_usage();
+                println!("unable to write {}: {}", out.display(), e);
                 std::process::exit(1);
             },
         }
+    } else {
+        run_sdl(&mut gameboy, speed);
     }
 }
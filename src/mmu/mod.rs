@@ -11,11 +11,15 @@
 // ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
 // ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
 
+use crate::bus::Bus;
 use crate::cartridge::Cartridge;
+use crate::dma::Dma;
 use crate::intc::InterruptController;
 use crate::joypad::Joypad;
 use crate::memory::Memory;
+use crate::ppu::Ppu;
 use crate::serial::Serial;
+use crate::timer::Timer;
 
 const WRAM_BASE: usize = 0xc000;
 const WRAM_BANK_BASE: usize = 0xd000;
@@ -23,6 +27,10 @@ const WRAM_SIZE: usize = 4096;
 const HRAM_BASE: usize = 0xff80;
 const HRAM_SIZE: usize = 127;
 const NUM_WRAM_BANKS: usize = 8;
+const ECHO_OFFSET: u16 = 0x2000;
+
+const KEY1_ARMED: u8 = 0;
+const KEY1_DOUBLE_SPEED: u8 = 7;
 
 /*
  * Memory Map
@@ -45,16 +53,25 @@ pub struct Mmu {
     pub cartridge: Cartridge,
     pub intc: InterruptController,
     pub joypad: Joypad,
-    serial: Serial,
+    pub ppu: Ppu,
+    pub timer: Timer,
+    pub serial: Serial,
+    dma: Dma,
     wram: [[u8; WRAM_SIZE]; NUM_WRAM_BANKS],
     svbk: usize,
     hram: [u8; HRAM_SIZE],
+
+    cgb: bool,
+    key1_armed: bool,
+    double_speed: bool,
 }
 
 impl Memory for Mmu {
     fn mem_read_byte(&self, addr: u16) -> u8 {
         match addr {
             0x0000..=0x7fff => self.cartridge.mem_read_byte(addr),
+            0x8000..=0x9fff => self.ppu.mem_read_byte(addr),
+            0xa000..=0xbfff => self.cartridge.mem_read_byte(addr),
             0xc000..=0xcfff => {
                 let idx = (addr as usize) - WRAM_BASE;
                 self.wram[0][idx]
@@ -64,21 +81,31 @@ impl Memory for Mmu {
                 let bank = if self.svbk == 0 { 1 } else { self.svbk };
                 self.wram[bank][idx]
             },
+            0xe000..=0xfdff => self.mem_read_byte(addr - ECHO_OFFSET),
+            0xfe00..=0xfe9f => self.ppu.mem_read_byte(addr),
+            0xfea0..=0xfeff => 0x00,
             0xff00 => self.joypad.mem_read_byte(addr),
             0xff01..=0xff02 => self.serial.mem_read_byte(addr),
+            0xff04..=0xff07 => self.timer.mem_read_byte(addr),
+            0xff40..=0xff45 | 0xff47..=0xff4b => self.ppu.mem_read_byte(addr),
+            0xff46 => self.dma.mem_read_byte(addr),
+            0xff4d => self.read_key1(),
             0xff70 => self.svbk as u8,
             0xff80..=0xfffe => {
                 let idx = (addr as usize) - HRAM_BASE;
                 self.hram[idx]
             },
             0xff0f | 0xffff => self.intc.mem_read_byte(addr),
-            _ => panic!("read from unmapped address {:#06x}", addr),
+            // unmapped IO, including sound which isn't emulated
+            _ => 0xff,
         }
     }
 
     fn mem_write_byte(&mut self, addr: u16, val: u8) {
         match addr {
             0x0000..=0x7fff => self.cartridge.mem_write_byte(addr, val),
+            0x8000..=0x9fff => self.ppu.mem_write_byte(addr, val),
+            0xa000..=0xbfff => self.cartridge.mem_write_byte(addr, val),
             0xc000..=0xcfff => {
                 let idx = (addr as usize) - WRAM_BASE;
                 self.wram[0][idx] = val;
@@ -88,29 +115,107 @@ impl Memory for Mmu {
                 let bank = if self.svbk == 0 { 1 } else { self.svbk };
                 self.wram[bank][idx] = val;
             },
+            0xe000..=0xfdff => self.mem_write_byte(addr - ECHO_OFFSET, val),
+            0xfe00..=0xfe9f => self.ppu.mem_write_byte(addr, val),
+            0xfea0..=0xfeff => {},
             0xff00 => self.joypad.mem_write_byte(addr, val),
             0xff01..=0xff02 => self.serial.mem_write_byte(addr, val),
+            0xff04..=0xff07 => self.timer.mem_write_byte(addr, val),
+            0xff40..=0xff45 | 0xff47..=0xff4b => self.ppu.mem_write_byte(addr, val),
+            0xff46 => self.dma.mem_write_byte(addr, val),
+            // only the armed bit is writable, the switch happens on STOP
+            0xff4d if self.cgb => self.key1_armed = (val & (1 << KEY1_ARMED)) != 0,
             0xff70 => self.svbk = (val & 0x7) as usize,
             0xff80..=0xfffe => {
                 let idx = (addr as usize) - HRAM_BASE;
                 self.hram[idx] = val;
             },
             0xff0f | 0xffff => self.intc.mem_write_byte(addr, val),
-            _ => panic!("write to unmapped address {:#06x}", addr),
+            _ => {},
         }
     }
 }
 
+impl Bus for Mmu {
+    fn ime(&self) -> bool {
+        self.intc.get_ime()
+    }
+
+    fn set_ime(&mut self, ime: bool) {
+        self.intc.set_ime(ime);
+    }
+}
+
 impl Mmu {
     pub fn new() -> Mmu {
         Mmu {
             cartridge: Cartridge::new(),
             intc: InterruptController::new(),
             joypad: Joypad::new(),
+            ppu: Ppu::new(),
+            timer: Timer::new(),
             serial: Serial::new(),
+            dma: Dma::new(),
             wram: [[0; WRAM_SIZE]; NUM_WRAM_BANKS],
             svbk: 0,
             hram: [0; HRAM_SIZE],
+            cgb: false,
+            key1_armed: false,
+            double_speed: false,
+        }
+    }
+
+    // CGB only registers like KEY1 are only present when running a CGB game
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+        self.serial.set_cgb(cgb);
+        self.key1_armed = false;
+        self.double_speed = false;
+    }
+
+    pub fn double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    /*
+     * KEY1: CGB speed switch
+     *
+     * bit 7: current speed, 1 is double speed (read only)
+     * bit 0: switch armed, the switch is performed by the next STOP
+     */
+    fn read_key1(&self) -> u8 {
+        if !self.cgb {
+            return 0xff;
+        }
+
+        0x7e | ((self.double_speed as u8) << KEY1_DOUBLE_SPEED) | ((self.key1_armed as u8) << KEY1_ARMED)
+    }
+
+    // called when the CPU executes STOP, returns true if STOP was used to
+    // switch speed rather than to enter low power mode
+    pub fn speed_switch(&mut self) -> bool {
+        if !(self.cgb && self.key1_armed) {
+            return false;
+        }
+
+        self.key1_armed = false;
+        self.double_speed = !self.double_speed;
+        true
+    }
+
+    // advance OAM DMA by the given number of M-cycles
+    pub fn step_dma(&mut self, cycles: usize) {
+        for _ in 0..cycles {
+            match self.dma.next_transfer() {
+                Some((src, offset)) => {
+                    let val = self.mem_read_byte(src);
+                    self.ppu.write_oam(offset, val);
+                },
+                None => break,
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod test;
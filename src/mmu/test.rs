@@ -0,0 +1,70 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+use super::*;
+
+const KEY1: u16 = 0xff4d;
+
+// Verify KEY1 is absent when not running a CGB game
+#[test]
+fn test_key1_dmg() {
+    let mut mmu = Mmu::new();
+
+    mmu.mem_write_byte(KEY1, 0x01);
+    assert_eq!(mmu.mem_read_byte(KEY1), 0xff);
+    assert!(!mmu.speed_switch());
+    assert!(!mmu.double_speed());
+}
+
+// Verify arming KEY1 switches speed on the next STOP only
+#[test]
+fn test_key1_speed_switch() {
+    let mut mmu = Mmu::new();
+    mmu.set_cgb(true);
+
+    assert_eq!(mmu.mem_read_byte(KEY1), 0x7e);
+    assert!(!mmu.speed_switch());
+
+    mmu.mem_write_byte(KEY1, 0x01);
+    assert_eq!(mmu.mem_read_byte(KEY1), 0x7f);
+
+    assert!(mmu.speed_switch());
+    assert!(mmu.double_speed());
+    assert_eq!(mmu.mem_read_byte(KEY1), 0xfe);
+    assert!(!mmu.speed_switch());
+
+    // switching back needs arming again
+    mmu.mem_write_byte(KEY1, 0x01);
+    assert!(mmu.speed_switch());
+    assert!(!mmu.double_speed());
+    assert_eq!(mmu.mem_read_byte(KEY1), 0x7e);
+}
+
+// Verify echo RAM mirrors WRAM and unmapped areas don't panic
+#[test]
+fn test_echo_and_unmapped() {
+    let mut mmu = Mmu::new();
+
+    mmu.mem_write_byte(0xc123, 0x5a);
+    assert_eq!(mmu.mem_read_byte(0xe123), 0x5a);
+    mmu.mem_write_byte(0xfdff, 0xa5);
+    assert_eq!(mmu.mem_read_byte(0xddff), 0xa5);
+
+    mmu.mem_write_byte(0xfea0, 0x12);
+    assert_eq!(mmu.mem_read_byte(0xfea0), 0x00);
+
+    // sound registers aren't emulated
+    mmu.mem_write_byte(0xff26, 0x80);
+    assert_eq!(mmu.mem_read_byte(0xff26), 0xff);
+}
@@ -0,0 +1,544 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use crate::int_src::IntReq;
+use crate::memory::Memory;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+// RGB values for the 4 DMG shades, lightest to darkest
+pub const PALETTE: [[u8; 3]; 4] = [
+    [0x9b, 0xbc, 0x0f],
+    [0x8b, 0xac, 0x0f],
+    [0x30, 0x62, 0x30],
+    [0x0f, 0x38, 0x0f],
+];
+
+const VRAM_BASE: usize = 0x8000;
+const VRAM_SIZE: usize = 8192;
+const OAM_BASE: usize = 0xfe00;
+const OAM_SIZE: usize = 160;
+
+const OAM_SCAN_DOTS: usize = 80;
+const DRAWING_DOTS: usize = 172;
+const SCANLINE_DOTS: usize = 456;
+const NUM_LINES: u8 = 154;
+const MAX_SPRITES_PER_LINE: usize = 10;
+
+/*
+ * LCDC bits
+ *
+ * 7: LCD and PPU enable
+ * 6: Window tile map area,          0=9800-9BFF, 1=9C00-9FFF
+ * 5: Window enable
+ * 4: BG and Window tile data area,  0=8800-97FF, 1=8000-8FFF
+ * 3: BG tile map area,              0=9800-9BFF, 1=9C00-9FFF
+ * 2: OBJ size,                      0=8x8, 1=8x16
+ * 1: OBJ enable
+ * 0: BG and Window enable
+ */
+const LCDC_ENABLE: u8 = 7;
+const LCDC_WIN_MAP: u8 = 6;
+const LCDC_WIN_ENABLE: u8 = 5;
+const LCDC_TILE_DATA: u8 = 4;
+const LCDC_BG_MAP: u8 = 3;
+const LCDC_OBJ_SIZE: u8 = 2;
+const LCDC_OBJ_ENABLE: u8 = 1;
+const LCDC_BG_ENABLE: u8 = 0;
+
+/*
+ * STAT bits
+ *
+ * 6:   LYC=LY interrupt source
+ * 5:   Mode 2 interrupt source
+ * 4:   Mode 1 interrupt source
+ * 3:   Mode 0 interrupt source
+ * 2:   LYC=LY flag
+ * 1-0: PPU mode
+ *
+ * only bits 3-6 are writable, the rest are driven by the PPU
+ */
+const STAT_WRITE_MASK: u8 = 0x78;
+const STAT_LYC_INT: u8 = 6;
+const STAT_MODE2_INT: u8 = 5;
+const STAT_MODE1_INT: u8 = 4;
+const STAT_MODE0_INT: u8 = 3;
+const STAT_LYC_EQ_LY: u8 = 2;
+
+const OBJ_ATTR_BG_PRIORITY: u8 = 7;
+const OBJ_ATTR_Y_FLIP: u8 = 6;
+const OBJ_ATTR_X_FLIP: u8 = 5;
+const OBJ_ATTR_PALETTE: u8 = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Sprite {
+    y: i16,
+    x: i16,
+    tile: u8,
+    attr: u8,
+    idx: usize,
+}
+
+pub struct Ppu {
+    pub vblank_int: IntReq,
+    pub stat_int: IntReq,
+    stat_line: bool,
+
+    vram: [u8; VRAM_SIZE],
+    oam: [u8; OAM_SIZE],
+    framebuffer: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+    back_buffer: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+    frame_ready: bool,
+
+    mode: Mode,
+    dots: usize,
+    window_line: u8,
+
+    lcdc: u8,
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+}
+
+impl Memory for Ppu {
+    fn mem_read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0x9fff => {
+                // VRAM is inaccessible to the CPU while the PPU is drawing
+                if self.mode == Mode::Drawing {
+                    0xff
+                } else {
+                    self.vram[(addr as usize) - VRAM_BASE]
+                }
+            },
+            0xfe00..=0xfe9f => {
+                // OAM is inaccessible to the CPU during OAM scan and drawing
+                if self.mode == Mode::OamScan || self.mode == Mode::Drawing {
+                    0xff
+                } else {
+                    self.oam[(addr as usize) - OAM_BASE]
+                }
+            },
+            0xff40 => self.lcdc,
+            0xff41 => self.read_stat(),
+            0xff42 => self.scy,
+            0xff43 => self.scx,
+            0xff44 => self.ly,
+            0xff45 => self.lyc,
+            0xff47 => self.bgp,
+            0xff48 => self.obp0,
+            0xff49 => self.obp1,
+            0xff4a => self.wy,
+            0xff4b => self.wx,
+            _ => panic!("read from invalid address: {:#06x}", addr),
+        }
+    }
+
+    fn mem_write_byte(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x8000..=0x9fff => {
+                if self.mode != Mode::Drawing {
+                    self.vram[(addr as usize) - VRAM_BASE] = val;
+                }
+            },
+            0xfe00..=0xfe9f => {
+                if self.mode != Mode::OamScan && self.mode != Mode::Drawing {
+                    self.oam[(addr as usize) - OAM_BASE] = val;
+                }
+            },
+            0xff40 => self.write_lcdc(val),
+            0xff41 => {
+                self.stat = (self.stat & !STAT_WRITE_MASK) | (val & STAT_WRITE_MASK);
+                self.update_stat_line();
+            },
+            0xff42 => self.scy = val,
+            0xff43 => self.scx = val,
+            0xff44 => {}, // LY is read only
+            0xff45 => {
+                self.lyc = val;
+                self.update_coincidence();
+            },
+            0xff47 => self.bgp = val,
+            0xff48 => self.obp0 = val,
+            0xff49 => self.obp1 = val,
+            0xff4a => self.wy = val,
+            0xff4b => self.wx = val,
+            _ => panic!("write to invalid address: {:#06x}", addr),
+        }
+    }
+}
+
+impl Default for Ppu {
+    fn default() -> Ppu {
+        Ppu::new()
+    }
+}
+
+impl Ppu {
+    pub fn new() -> Ppu {
+        Ppu {
+            vblank_int: IntReq::new(),
+            stat_int: IntReq::new(),
+            stat_line: false,
+
+            vram: [0; VRAM_SIZE],
+            oam: [0; OAM_SIZE],
+            framebuffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            back_buffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frame_ready: false,
+
+            mode: Mode::OamScan,
+            dots: 0,
+            window_line: 0,
+
+            // default DMG powerup state according to
+            // https://gbdev.io/pandocs/Power_Up_Sequence.html#hardware-registers
+            lcdc: 0x91,
+            stat: 0x00,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0xfc,
+            obp0: 0xff,
+            obp1: 0xff,
+            wy: 0,
+            wx: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    // OAM DMA writes bypass the CPU access restrictions
+    pub fn write_oam(&mut self, offset: usize, val: u8) {
+        self.oam[offset] = val;
+    }
+
+    // The last completed frame. Each entry is a shade from 0 (lightest)
+    // to 3 (darkest), after the BGP/OBP palettes have been applied
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    // returns true once per completed frame, i.e. when the PPU has
+    // entered VBLANK since the last call
+    pub fn check_and_consume_frame(&mut self) -> bool {
+        let ready = self.frame_ready;
+        self.frame_ready = false;
+        ready
+    }
+
+    // advance the PPU by the given number of dots (T-cycles)
+    pub fn step(&mut self, cycles: usize) {
+        if !self.lcd_enabled() {
+            return;
+        }
+
+        for _ in 0..cycles {
+            self.dots += 1;
+
+            match self.mode {
+                Mode::OamScan => {
+                    if self.dots == OAM_SCAN_DOTS {
+                        self.set_mode(Mode::Drawing);
+                    }
+                },
+                Mode::Drawing => {
+                    if self.dots == OAM_SCAN_DOTS + DRAWING_DOTS {
+                        self.render_scanline();
+                        self.set_mode(Mode::HBlank);
+                    }
+                },
+                Mode::HBlank => {
+                    if self.dots == SCANLINE_DOTS {
+                        self.next_line();
+
+                        if (self.ly as usize) == SCREEN_HEIGHT {
+                            // scanlines are drawn into the back buffer so
+                            // the frontend never sees a partial frame
+                            self.framebuffer = self.back_buffer;
+                            self.frame_ready = true;
+                            self.vblank_int.raise();
+                            self.set_mode(Mode::VBlank);
+                        } else {
+                            self.set_mode(Mode::OamScan);
+                        }
+                    }
+                },
+                Mode::VBlank => {
+                    if self.dots == SCANLINE_DOTS {
+                        self.next_line();
+
+                        if self.ly == 0 {
+                            self.window_line = 0;
+                            self.set_mode(Mode::OamScan);
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.lcdc_is_set(LCDC_ENABLE)
+    }
+
+    fn lcdc_is_set(&self, bit: u8) -> bool {
+        (self.lcdc & (1 << bit)) != 0
+    }
+
+    fn stat_is_set(&self, bit: u8) -> bool {
+        (self.stat & (1 << bit)) != 0
+    }
+
+    fn write_lcdc(&mut self, val: u8) {
+        let was_enabled = self.lcd_enabled();
+        self.lcdc = val;
+
+        if was_enabled && !self.lcd_enabled() {
+            // turning the LCD off resets the PPU to the top of the screen
+            self.ly = 0;
+            self.dots = 0;
+            self.window_line = 0;
+            self.set_mode(Mode::HBlank);
+        } else if !was_enabled && self.lcd_enabled() {
+            self.ly = 0;
+            self.dots = 0;
+            self.window_line = 0;
+            self.set_mode(Mode::OamScan);
+            self.update_coincidence();
+        }
+    }
+
+    fn read_stat(&self) -> u8 {
+        // bit 7 is unused and always reads back as 1
+        0x80 | (self.stat & !0x03) | (self.mode as u8)
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.update_stat_line();
+    }
+
+    // All STAT interrupt sources are ORed together into a single line and
+    // the interrupt is only requested on its rising edge. While one source
+    // holds the line high, any other source becoming active is "blocked".
+    fn update_stat_line(&mut self) {
+        let line = self.lcd_enabled() && (
+            (self.stat_is_set(STAT_LYC_INT) && self.stat_is_set(STAT_LYC_EQ_LY))
+            || (self.stat_is_set(STAT_MODE0_INT) && self.mode == Mode::HBlank)
+            || (self.stat_is_set(STAT_MODE1_INT) && self.mode == Mode::VBlank)
+            || (self.stat_is_set(STAT_MODE2_INT) && self.mode == Mode::OamScan));
+
+        if line && !self.stat_line {
+            self.stat_int.raise();
+        }
+
+        self.stat_line = line;
+    }
+
+    fn next_line(&mut self) {
+        self.dots = 0;
+        self.ly = (self.ly + 1) % NUM_LINES;
+        self.update_coincidence();
+    }
+
+    fn update_coincidence(&mut self) {
+        if self.ly == self.lyc {
+            self.stat |= 1 << STAT_LYC_EQ_LY;
+        } else {
+            self.stat &= !(1 << STAT_LYC_EQ_LY);
+        }
+
+        self.update_stat_line();
+    }
+
+    fn read_vram(&self, addr: u16) -> u8 {
+        self.vram[(addr as usize) - VRAM_BASE]
+    }
+
+    // returns the 2 bit color index of pixel (x, y) within the given tile
+    fn tile_pixel(&self, tile_addr: u16, x: u8, y: u8) -> u8 {
+        let lo = self.read_vram(tile_addr + (y as u16) * 2);
+        let hi = self.read_vram(tile_addr + (y as u16) * 2 + 1);
+        let bit = 7 - x;
+
+        (((hi >> bit) & 1) << 1) | ((lo >> bit) & 1)
+    }
+
+    // BG and window tiles are addressed either unsigned from 0x8000 or
+    // signed from 0x9000 depending on LCDC bit 4
+    fn bg_tile_addr(&self, tile: u8) -> u16 {
+        if self.lcdc_is_set(LCDC_TILE_DATA) {
+            0x8000 + (tile as u16) * 16
+        } else {
+            (0x9000 + (tile as i8 as i32) * 16) as u16
+        }
+    }
+
+    fn map_pixel(&self, map_base: u16, x: u8, y: u8) -> u8 {
+        let map_addr = map_base + ((y as u16) / 8) * 32 + ((x as u16) / 8);
+        let tile = self.read_vram(map_addr);
+
+        self.tile_pixel(self.bg_tile_addr(tile), x % 8, y % 8)
+    }
+
+    fn apply_palette(palette: u8, color: u8) -> u8 {
+        (palette >> (color * 2)) & 0x3
+    }
+
+    fn render_scanline(&mut self) {
+        let ly = self.ly;
+        let mut bg_colors = [0u8; SCREEN_WIDTH];
+
+        // background and window. on DMG, LCDC bit 0 disables both
+        if self.lcdc_is_set(LCDC_BG_ENABLE) {
+            let bg_map = if self.lcdc_is_set(LCDC_BG_MAP) { 0x9c00 } else { 0x9800 };
+            let win_map = if self.lcdc_is_set(LCDC_WIN_MAP) { 0x9c00 } else { 0x9800 };
+            let win_visible = self.lcdc_is_set(LCDC_WIN_ENABLE)
+                && ly >= self.wy
+                && self.wx <= 166;
+
+            for (x, color) in bg_colors.iter_mut().enumerate() {
+                let in_window = win_visible && (x as i16) >= (self.wx as i16) - 7;
+
+                *color = if in_window {
+                    let win_x = ((x as i16) - ((self.wx as i16) - 7)) as u8;
+                    self.map_pixel(win_map, win_x, self.window_line)
+                } else {
+                    self.map_pixel(bg_map,
+                                   (x as u8).wrapping_add(self.scx),
+                                   ly.wrapping_add(self.scy))
+                };
+            }
+
+            // the window keeps its own line counter which only advances
+            // on lines where it was actually drawn
+            if win_visible {
+                self.window_line = self.window_line.wrapping_add(1);
+            }
+        }
+
+        // with the background off the line is blank (shade 0) whatever BGP
+        // says, but sprites still see color 0 underneath them
+        let line = (ly as usize) * SCREEN_WIDTH;
+        let bg_enabled = self.lcdc_is_set(LCDC_BG_ENABLE);
+        for (x, color) in bg_colors.iter().enumerate() {
+            self.back_buffer[line + x] = if bg_enabled {
+                Ppu::apply_palette(self.bgp, *color)
+            } else {
+                0
+            };
+        }
+
+        if self.lcdc_is_set(LCDC_OBJ_ENABLE) {
+            self.render_sprites(&bg_colors);
+        }
+    }
+
+    fn scan_oam(&self) -> Vec<Sprite> {
+        let height: i16 = if self.lcdc_is_set(LCDC_OBJ_SIZE) { 16 } else { 8 };
+        let ly = self.ly as i16;
+
+        let mut sprites: Vec<Sprite> = self.oam
+            .chunks(4)
+            .enumerate()
+            .map(|(idx, entry)| Sprite {
+                y: (entry[0] as i16) - 16,
+                x: (entry[1] as i16) - 8,
+                tile: entry[2],
+                attr: entry[3],
+                idx,
+            })
+            .filter(|s| ly >= s.y && ly < s.y + height)
+            .take(MAX_SPRITES_PER_LINE)
+            .collect();
+
+        // on DMG the sprite with the lowest X coordinate wins, ties are
+        // broken by position in OAM
+        sprites.sort_by_key(|s| (s.x, s.idx));
+        sprites
+    }
+
+    fn render_sprites(&mut self, bg_colors: &[u8; SCREEN_WIDTH]) {
+        let tall = self.lcdc_is_set(LCDC_OBJ_SIZE);
+        let height: i16 = if tall { 16 } else { 8 };
+        let ly = self.ly as i16;
+        let sprites = self.scan_oam();
+        let line = (self.ly as usize) * SCREEN_WIDTH;
+
+        for x in 0..(SCREEN_WIDTH as i16) {
+            for sprite in sprites.iter() {
+                if x < sprite.x || x >= sprite.x + 8 {
+                    continue;
+                }
+
+                let mut row = ly - sprite.y;
+                if (sprite.attr & (1 << OBJ_ATTR_Y_FLIP)) != 0 {
+                    row = height - 1 - row;
+                }
+
+                let mut col = x - sprite.x;
+                if (sprite.attr & (1 << OBJ_ATTR_X_FLIP)) != 0 {
+                    col = 7 - col;
+                }
+
+                // in 8x16 mode bit 0 of the tile index is ignored
+                let tile = if tall { sprite.tile & 0xfe } else { sprite.tile };
+                let tile_addr = 0x8000 + (tile as u16) * 16;
+                let color = self.tile_pixel(tile_addr, col as u8, row as u8);
+
+                // color 0 is transparent for sprites, let lower priority
+                // sprites show through
+                if color == 0 {
+                    continue;
+                }
+
+                let behind_bg = (sprite.attr & (1 << OBJ_ATTR_BG_PRIORITY)) != 0;
+                if !(behind_bg && bg_colors[x as usize] != 0) {
+                    let palette = if (sprite.attr & (1 << OBJ_ATTR_PALETTE)) != 0 {
+                        self.obp1
+                    } else {
+                        self.obp0
+                    };
+
+                    self.back_buffer[line + x as usize] = Ppu::apply_palette(palette, color);
+                }
+
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,264 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use super::*;
+use crate::int_src::InterruptSource;
+
+const LCDC: u16 = 0xff40;
+const STAT: u16 = 0xff41;
+const SCX: u16 = 0xff43;
+const LY: u16 = 0xff44;
+const LYC: u16 = 0xff45;
+const BGP: u16 = 0xff47;
+const OBP0: u16 = 0xff48;
+
+// Write a tile where every pixel has the given color index
+fn fill_tile(ppu: &mut Ppu, addr: u16, color: u8) {
+    let lo = if (color & 1) != 0 { 0xff } else { 0x00 };
+    let hi = if (color & 2) != 0 { 0xff } else { 0x00 };
+
+    for row in 0..8 {
+        ppu.mem_write_byte(addr + row * 2, lo);
+        ppu.mem_write_byte(addr + row * 2 + 1, hi);
+    }
+}
+
+// Turn the LCD off so VRAM/OAM can be freely written, then hand back
+// control with the given LCDC value
+fn setup_vram<F: FnOnce(&mut Ppu)>(ppu: &mut Ppu, lcdc: u8, setup: F) {
+    ppu.mem_write_byte(LCDC, 0x00);
+    setup(ppu);
+    ppu.mem_write_byte(LCDC, lcdc);
+}
+
+// Verify mode sequence and timing across a single scanline
+#[test]
+fn test_mode_timing() {
+    let mut ppu = Ppu::new();
+    assert_eq!(ppu.mode(), Mode::OamScan);
+
+    ppu.step(79);
+    assert_eq!(ppu.mode(), Mode::OamScan);
+    ppu.step(1);
+    assert_eq!(ppu.mode(), Mode::Drawing);
+
+    ppu.step(172);
+    assert_eq!(ppu.mode(), Mode::HBlank);
+    assert_eq!(ppu.mem_read_byte(STAT) & 0x3, Mode::HBlank as u8);
+
+    ppu.step(204);
+    assert_eq!(ppu.mode(), Mode::OamScan);
+    assert_eq!(ppu.mem_read_byte(LY), 1);
+}
+
+// Verify LY wraps after 154 lines and a frame is reported at VBLANK
+#[test]
+fn test_frame_timing() {
+    let mut ppu = Ppu::new();
+
+    ppu.step(456 * 144 - 1);
+    assert!(!ppu.check_and_consume_frame());
+    assert_eq!(ppu.mem_read_byte(LY), 143);
+
+    ppu.step(1);
+    assert_eq!(ppu.mode(), Mode::VBlank);
+    assert_eq!(ppu.mem_read_byte(LY), 144);
+    assert!(ppu.check_and_consume_frame());
+    assert!(!ppu.check_and_consume_frame());
+
+    ppu.step(456 * 10);
+    assert_eq!(ppu.mode(), Mode::OamScan);
+    assert_eq!(ppu.mem_read_byte(LY), 0);
+}
+
+// Verify STAT reports the LY=LYC coincidence flag
+#[test]
+fn test_lyc_coincidence() {
+    let mut ppu = Ppu::new();
+
+    ppu.mem_write_byte(LYC, 2);
+    assert_eq!(ppu.mem_read_byte(STAT) & (1 << STAT_LYC_EQ_LY), 0);
+
+    ppu.step(456 * 2);
+    assert_ne!(ppu.mem_read_byte(STAT) & (1 << STAT_LYC_EQ_LY), 0);
+
+    ppu.step(456);
+    assert_eq!(ppu.mem_read_byte(STAT) & (1 << STAT_LYC_EQ_LY), 0);
+}
+
+// Verify VRAM is locked while drawing and LY is reset when LCD is off
+#[test]
+fn test_vram_access() {
+    let mut ppu = Ppu::new();
+
+    ppu.mem_write_byte(0x8000, 0xa5);
+    assert_eq!(ppu.mem_read_byte(0x8000), 0xa5);
+
+    ppu.step(80);
+    assert_eq!(ppu.mode(), Mode::Drawing);
+    assert_eq!(ppu.mem_read_byte(0x8000), 0xff);
+    ppu.mem_write_byte(0x8000, 0x5a);
+
+    ppu.step(456 * 3);
+    ppu.mem_write_byte(LCDC, 0x00);
+    assert_eq!(ppu.mem_read_byte(LY), 0);
+    assert_eq!(ppu.mem_read_byte(0x8000), 0xa5);
+}
+
+// Verify background rendering with scrolling and palette
+#[test]
+fn test_render_background() {
+    let mut ppu = Ppu::new();
+
+    setup_vram(&mut ppu, 0x91, |ppu| {
+        // tile 0 is color 0, tile 1 is color 3
+        fill_tile(ppu, 0x8000, 0);
+        fill_tile(ppu, 0x8010, 3);
+        ppu.mem_write_byte(0x9801, 1);
+        ppu.mem_write_byte(BGP, 0xe4);
+        ppu.mem_write_byte(SCX, 4);
+    });
+
+    ppu.step(456 * 144);
+    let fb = ppu.framebuffer();
+    assert_eq!(fb[0], 0);
+    assert_eq!(fb[3], 0);
+    assert_eq!(fb[4], 3);
+    assert_eq!(fb[11], 3);
+    assert_eq!(fb[12], 0);
+}
+
+// Verify sprites are drawn over color 0 and hidden behind BG when requested
+#[test]
+fn test_render_sprites() {
+    let mut ppu = Ppu::new();
+
+    setup_vram(&mut ppu, 0x93, |ppu| {
+        fill_tile(ppu, 0x8000, 0);
+        fill_tile(ppu, 0x8010, 1);
+        fill_tile(ppu, 0x8020, 2);
+        ppu.mem_write_byte(0x9801, 1);
+        ppu.mem_write_byte(BGP, 0xe4);
+        ppu.mem_write_byte(OBP0, 0xe4);
+
+        // sprite 0 at x=0, sprite 1 behind BG at x=8
+        ppu.mem_write_byte(0xfe00, 16);
+        ppu.mem_write_byte(0xfe01, 8);
+        ppu.mem_write_byte(0xfe02, 2);
+        ppu.mem_write_byte(0xfe03, 0);
+        ppu.mem_write_byte(0xfe04, 16);
+        ppu.mem_write_byte(0xfe05, 16);
+        ppu.mem_write_byte(0xfe06, 2);
+        ppu.mem_write_byte(0xfe07, 1 << OBJ_ATTR_BG_PRIORITY);
+    });
+
+    ppu.step(456 * 144);
+    let fb = ppu.framebuffer();
+    assert_eq!(fb[0], 2);
+    assert_eq!(fb[7], 2);
+    assert_eq!(fb[8], 1);
+    assert_eq!(fb[16], 0);
+}
+
+// Verify VBLANK is requested once when entering line 144
+#[test]
+fn test_vblank_interrupt() {
+    let mut ppu = Ppu::new();
+
+    ppu.step(456 * 144 - 1);
+    assert!(!ppu.vblank_int.check_and_consume_int_req());
+
+    ppu.step(1);
+    assert!(ppu.vblank_int.check_and_consume_int_req());
+    assert!(!ppu.vblank_int.check_and_consume_int_req());
+
+    ppu.step(456 * 10);
+    assert!(!ppu.vblank_int.check_and_consume_int_req());
+}
+
+// Verify STAT is requested for enabled mode and LYC sources
+#[test]
+fn test_stat_interrupt() {
+    let mut ppu = Ppu::new();
+
+    // mode 0 source fires when entering HBLANK
+    ppu.mem_write_byte(STAT, 1 << STAT_MODE0_INT);
+    ppu.step(252);
+    assert!(ppu.stat_int.check_and_consume_int_req());
+
+    // mode 2 source fires when entering OAM scan
+    ppu.mem_write_byte(STAT, 1 << STAT_MODE2_INT);
+    assert!(!ppu.stat_int.check_and_consume_int_req());
+    ppu.step(204);
+    assert!(ppu.stat_int.check_and_consume_int_req());
+
+    // LYC source fires when LY matches
+    ppu.mem_write_byte(STAT, 1 << STAT_LYC_INT);
+    ppu.mem_write_byte(LYC, 3);
+    ppu.step(456);
+    assert!(!ppu.stat_int.check_and_consume_int_req());
+    ppu.step(456);
+    assert!(ppu.stat_int.check_and_consume_int_req());
+}
+
+// Verify STAT sources block each other while the line is held high
+#[test]
+fn test_stat_blocking() {
+    let mut ppu = Ppu::new();
+
+    // mode 0 holds the line high through the end of line 0, so LY=LYC
+    // becoming true at the start of line 1 does not make a new edge
+    ppu.mem_write_byte(LYC, 1);
+    ppu.mem_write_byte(STAT, (1 << STAT_MODE0_INT) | (1 << STAT_LYC_INT));
+    ppu.step(252);
+    assert!(ppu.stat_int.check_and_consume_int_req());
+
+    ppu.step(204);
+    assert_eq!(ppu.mem_read_byte(LY), 1);
+    assert!(!ppu.stat_int.check_and_consume_int_req());
+
+    // LY=LYC stays high through line 1, so its HBLANK is blocked too
+    ppu.step(252);
+    assert!(!ppu.stat_int.check_and_consume_int_req());
+
+    // line 2 drops LY=LYC during OAM scan, so line 2 HBLANK fires again
+    ppu.step(204 + 252);
+    assert!(ppu.stat_int.check_and_consume_int_req());
+}
+
+// Verify a disabled background is blank regardless of BGP, with sprites
+// still drawn over it
+#[test]
+fn test_render_background_disabled() {
+    let mut ppu = Ppu::new();
+
+    setup_vram(&mut ppu, 0x92, |ppu| {
+        fill_tile(ppu, 0x8000, 3);
+        fill_tile(ppu, 0x8010, 2);
+        ppu.mem_write_byte(BGP, 0xff);
+        ppu.mem_write_byte(OBP0, 0xe4);
+
+        // sprite behind BG at x=0, still visible since BG color is 0
+        ppu.mem_write_byte(0xfe00, 16);
+        ppu.mem_write_byte(0xfe01, 8);
+        ppu.mem_write_byte(0xfe02, 1);
+        ppu.mem_write_byte(0xfe03, 1 << OBJ_ATTR_BG_PRIORITY);
+    });
+
+    ppu.step(456 * 144);
+    let fb = ppu.framebuffer();
+    assert_eq!(fb[0], 2);
+    assert_eq!(fb[7], 2);
+    assert_eq!(fb[8], 0);
+    assert_eq!(fb[SCREEN_WIDTH * 100], 0);
+}
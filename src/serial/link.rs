@@ -0,0 +1,46 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+
+// Whatever is plugged into the other end of the link port. Every transfer
+// is clocked by one of the two ends, the other end answers with the byte
+// in its own SB at the same time.
+pub trait LinkCable: Send {
+    // a transfer clocked by this end has started, shift out a byte
+    fn start_transfer(&mut self, out: u8);
+
+    // the byte shifted in for the last start_transfer. None means the other
+    // end hasn't answered yet and the transfer stalls until it does
+    fn finish_transfer(&mut self) -> Option<u8>;
+
+    // called while this end is not clocking a transfer. if the other end
+    // has clocked one answer it with reply and return the byte it sent
+    fn external_transfer(&mut self, reply: u8) -> Option<u8>;
+}
+
+// Nothing plugged in, the data line floats high
+pub struct NullLink;
+
+impl LinkCable for NullLink {
+    fn start_transfer(&mut self, _out: u8) {
+    }
+
+    fn finish_transfer(&mut self) -> Option<u8> {
+        Some(0xff)
+    }
+
+    fn external_transfer(&mut self, _reply: u8) -> Option<u8> {
+        None
+    }
+}
@@ -11,24 +11,186 @@
 // ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
 // ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
 
+use std::io::Write;
+
+use crate::int_src::InterruptSource;
 use crate::memory::Memory;
 
+pub mod link;
+pub mod peer;
+
+use self::link::{LinkCable, NullLink};
+
+const SC_START: u8 = 7;
+const SC_FAST_CLOCK: u8 = 1;
+const SC_INTERNAL_CLOCK: u8 = 0;
+
+// T-cycles per bit for the 8192Hz and CGB 262144Hz internal clocks. both
+// double along with the CPU in double speed mode
+const BIT_CYCLES: usize = 512;
+const FAST_BIT_CYCLES: usize = 16;
+
+/*
+ * Serial
+ *
+ * SB (FF01): byte to send, replaced by the byte received once the
+ *            transfer is done
+ * SC (FF02): bit 7 starts a transfer and stays set until it's done
+ *            bit 1 selects the 262144Hz clock (CGB only)
+ *            bit 0 selects the internal clock, otherwise the other end
+ *            of the link cable clocks the transfer
+ *
+ * Bits are shifted out one at a time but the other end only sees whole
+ * bytes, exchanged through the LinkCable once all 8 bits have been clocked.
+ * The SERIAL interrupt is requested when the transfer is done.
+ */
 pub struct Serial {
+    sb: u8,
+    sc: u8,
+    cgb: bool,
+    cycles_left: usize,
+    cable: Box<dyn LinkCable>,
+    output: Option<Vec<u8>>,
+    tee: Option<Box<dyn Write + Send>>,
+    int_req: bool,
+}
+
+impl InterruptSource for Serial {
+    fn check_int_req(&self) -> bool {
+        self.int_req
+    }
 
+    fn consume_int_req(&mut self) {
+        self.int_req = false;
+    }
 }
 
 impl Memory for Serial {
-    fn mem_read_byte(&self, _addr: u16) -> u8 {
-        0xff
+    fn mem_read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0xff01 => self.sb,
+            0xff02 => 0x7c | self.sc | if self.cgb { 0 } else { 1 << SC_FAST_CLOCK },
+            _ => panic!("read from invalid address: {:#06x}", addr),
+        }
     }
 
-    fn mem_write_byte(&mut self, _addr: u16, _val: u8) {
-        // do nothing
+    fn mem_write_byte(&mut self, addr: u16, val: u8) {
+        match addr {
+            0xff01 => self.sb = val,
+            0xff02 => {
+                let mut mask = (1 << SC_START) | (1 << SC_INTERNAL_CLOCK);
+                if self.cgb {
+                    mask |= 1 << SC_FAST_CLOCK;
+                }
+                self.sc = val & mask;
+
+                if self.internal_transfer() {
+                    self.start_transfer();
+                }
+            },
+            _ => panic!("write to invalid address: {:#06x}", addr),
+        }
+    }
+}
+
+impl Default for Serial {
+    fn default() -> Serial {
+        Serial::new()
     }
 }
 
 impl Serial {
     pub fn new() -> Serial {
-        Serial { }
+        Serial {
+            sb: 0,
+            sc: 0,
+            cgb: false,
+            cycles_left: 0,
+            cable: Box::new(NullLink),
+            output: None,
+            tee: None,
+            int_req: false,
+        }
+    }
+
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+    }
+
+    pub fn set_link_cable(&mut self, cable: Box<dyn LinkCable>) {
+        self.cable = cable;
+    }
+
+    // copy every byte sent to the given writer as well as the link cable
+    pub fn set_tee(&mut self, tee: Option<Box<dyn Write + Send>>) {
+        self.tee = tee;
+    }
+
+    // advance the serial port by the given number of T-cycles
+    pub fn step(&mut self, cycles: usize) {
+        if self.internal_transfer() {
+            self.cycles_left = self.cycles_left.saturating_sub(cycles);
+
+            if self.cycles_left == 0 {
+                if let Some(val) = self.cable.finish_transfer() {
+                    self.finish_transfer(val);
+                }
+            }
+        } else {
+            // the other end can clock a transfer at any time, if we're not
+            // waiting on one it sees nothing but 1s
+            let waiting = (self.sc & (1 << SC_START)) != 0;
+            let reply = if waiting { self.sb } else { 0xff };
+
+            if let Some(val) = self.cable.external_transfer(reply) {
+                if waiting {
+                    self.finish_transfer(val);
+                }
+            }
+        }
+    }
+
+    // keep every byte sent for take_output, off by default so nothing
+    // piles up when no one is going to look at it
+    pub fn capture_output(&mut self, capture: bool) {
+        self.output = if capture { Some(Vec::new()) } else { None };
+    }
+
+    // bytes sent since the last call, if capturing
+    pub fn take_output(&mut self) -> Vec<u8> {
+        self.output.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn internal_transfer(&self) -> bool {
+        let bits = (1 << SC_START) | (1 << SC_INTERNAL_CLOCK);
+        (self.sc & bits) == bits
+    }
+
+    fn start_transfer(&mut self) {
+        let bit_cycles = if (self.sc & (1 << SC_FAST_CLOCK)) != 0 {
+            FAST_BIT_CYCLES
+        } else {
+            BIT_CYCLES
+        };
+
+        self.cycles_left = bit_cycles * 8;
+        if let Some(output) = &mut self.output {
+            output.push(self.sb);
+        }
+        self.cable.start_transfer(self.sb);
+
+        // flushed every byte so nothing is lost if we crash or get killed
+        if let Some(tee) = &mut self.tee {
+            tee.write_all(&[self.sb]).and_then(|_| tee.flush()).ok();
+        }
+    }
+
+    fn finish_transfer(&mut self, val: u8) {
+        self.sb = val;
+        self.sc &= !(1 << SC_START);
+        self.int_req = true;
     }
 }
+
+#[cfg(test)]
+mod test;
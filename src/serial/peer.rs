@@ -0,0 +1,185 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+#[cfg(unix)]
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use crate::serial::link::LinkCable;
+
+const PACKET_DATA: u8 = 0;
+const PACKET_REPLY: u8 = 1;
+
+#[derive(Debug, Copy, Clone)]
+enum Packet {
+    // the sender clocked a transfer, this is the byte it shifted out
+    Data(u8),
+    // answer to a Data packet with the byte the receiver shifted out
+    Reply(u8),
+}
+
+/*
+ * Link cable to another emulator
+ *
+ * The two ends run in lockstep one byte at a time. The end clocking a
+ * transfer sends its byte and the transfer doesn't finish until the other
+ * end has replied with the contents of its SB, so a game waiting on SC
+ * just spins until the other emulator catches up. The other end only
+ * accepts the byte if it's waiting on an external clock transfer,
+ * otherwise it replies with 0xFF the same as an unplugged cable.
+ *
+ * If both ends clock a transfer at the same time they both get 0xFF.
+ *
+ * Over a socket each packet is 2 bytes, a type followed by the data. A
+ * reader and writer thread move packets between the socket and the
+ * channels used here, so polling for them every step stays cheap.
+ */
+pub struct PeerLink {
+    tx: Sender<Packet>,
+    rx: Receiver<Packet>,
+}
+
+impl LinkCable for PeerLink {
+    fn start_transfer(&mut self, out: u8) {
+        self.send(Packet::Data(out));
+    }
+
+    fn finish_transfer(&mut self) -> Option<u8> {
+        loop {
+            match self.rx.try_recv() {
+                Ok(Packet::Reply(val)) => return Some(val),
+                // the other end is clocking its own transfer
+                Ok(Packet::Data(_)) => self.send(Packet::Reply(0xff)),
+                Err(TryRecvError::Empty) => return None,
+                // cable pulled out
+                Err(TryRecvError::Disconnected) => return Some(0xff),
+            }
+        }
+    }
+
+    fn external_transfer(&mut self, reply: u8) -> Option<u8> {
+        loop {
+            match self.rx.try_recv() {
+                Ok(Packet::Data(val)) => {
+                    self.send(Packet::Reply(reply));
+                    return Some(val);
+                },
+                // left over from a transfer that clashed with ours
+                Ok(Packet::Reply(_)) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+impl PeerLink {
+    // two ends joined directly, for linking emulators in the same process
+    pub fn loopback() -> (PeerLink, PeerLink) {
+        let (a_tx, b_rx) = channel();
+        let (b_tx, a_rx) = channel();
+
+        (PeerLink { tx: a_tx, rx: a_rx }, PeerLink { tx: b_tx, rx: b_rx })
+    }
+
+    // wait for the other emulator to connect
+    pub fn tcp_listen(addr: &str) -> io::Result<PeerLink> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        PeerLink::from_tcp(stream)
+    }
+
+    pub fn tcp_connect(addr: &str) -> io::Result<PeerLink> {
+        PeerLink::from_tcp(TcpStream::connect(addr)?)
+    }
+
+    // a socket left behind by a session that didn't exit cleanly is
+    // replaced, and the socket is removed again once the other end has
+    // connected so nothing is left behind this time
+    #[cfg(unix)]
+    pub fn unix_listen(path: &str) -> io::Result<PeerLink> {
+        if fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        let accepted = listener.accept();
+        fs::remove_file(path).ok();
+
+        let (stream, _) = accepted?;
+        Ok(PeerLink::from_stream(stream.try_clone()?, stream))
+    }
+
+    #[cfg(unix)]
+    pub fn unix_connect(path: &str) -> io::Result<PeerLink> {
+        let stream = UnixStream::connect(path)?;
+        Ok(PeerLink::from_stream(stream.try_clone()?, stream))
+    }
+
+    fn from_tcp(stream: TcpStream) -> io::Result<PeerLink> {
+        // every transfer is a round trip, don't let Nagle sit on them
+        stream.set_nodelay(true)?;
+        Ok(PeerLink::from_stream(stream.try_clone()?, stream))
+    }
+
+    fn from_stream<R, W>(mut reader: R, mut writer: W) -> PeerLink
+        where R: Read + Send + 'static,
+              W: Write + Send + 'static
+    {
+        let (in_tx, in_rx) = channel();
+        let (out_tx, out_rx) = channel();
+
+        // both threads exit once the socket or the PeerLink goes away
+        thread::spawn(move || {
+            let mut buf = [0u8; 2];
+            while reader.read_exact(&mut buf).is_ok() {
+                let packet = match buf[0] {
+                    PACKET_DATA => Packet::Data(buf[1]),
+                    PACKET_REPLY => Packet::Reply(buf[1]),
+                    _ => break,
+                };
+
+                if in_tx.send(packet).is_err() {
+                    break;
+                }
+            }
+        });
+
+        thread::spawn(move || {
+            for packet in out_rx {
+                let buf = match packet {
+                    Packet::Data(val) => [PACKET_DATA, val],
+                    Packet::Reply(val) => [PACKET_REPLY, val],
+                };
+
+                if writer.write_all(&buf).is_err() {
+                    break;
+                }
+            }
+        });
+
+        PeerLink { tx: out_tx, rx: in_rx }
+    }
+
+    fn send(&mut self, packet: Packet) {
+        // nothing to do if the other end has gone, finish_transfer will
+        // notice and treat it as an unplugged cable
+        self.tx.send(packet).ok();
+    }
+}
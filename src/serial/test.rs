@@ -0,0 +1,280 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+use std::sync::{Arc, Mutex};
+
+use super::*;
+use super::peer::PeerLink;
+
+const SB: u16 = 0xff01;
+const SC: u16 = 0xff02;
+
+// Link cable which records what it's sent and answers with a fixed byte.
+// external transfers are queued up in incoming
+struct TestLink {
+    sent: Arc<Mutex<Vec<u8>>>,
+    incoming: Arc<Mutex<Vec<u8>>>,
+    replies: Arc<Mutex<Vec<u8>>>,
+    answer: u8,
+}
+
+impl LinkCable for TestLink {
+    fn start_transfer(&mut self, out: u8) {
+        self.sent.lock().unwrap().push(out);
+    }
+
+    fn finish_transfer(&mut self) -> Option<u8> {
+        Some(self.answer)
+    }
+
+    fn external_transfer(&mut self, reply: u8) -> Option<u8> {
+        let val = self.incoming.lock().unwrap().pop()?;
+        self.replies.lock().unwrap().push(reply);
+        Some(val)
+    }
+}
+
+fn start(serial: &mut Serial, sb: u8, sc: u8) {
+    serial.mem_write_byte(SB, sb);
+    serial.mem_write_byte(SC, sc);
+}
+
+// Verify internal clock transfers take 8 bits at 8192Hz
+#[test]
+fn test_transfer_timing() {
+    let mut serial = Serial::new();
+    serial.capture_output(true);
+
+    start(&mut serial, b'H', 0x81);
+    assert_eq!(serial.mem_read_byte(SC), 0xff);
+
+    serial.step(512 * 8 - 4);
+    assert_eq!(serial.mem_read_byte(SC), 0xff);
+    assert!(!serial.check_and_consume_int_req());
+
+    serial.step(4);
+    assert_eq!(serial.mem_read_byte(SC), 0x7f);
+    assert_eq!(serial.mem_read_byte(SB), 0xff);
+    assert!(serial.check_and_consume_int_req());
+
+    assert_eq!(serial.take_output(), b"H");
+    assert!(serial.take_output().is_empty());
+
+    // nothing is kept once capturing is turned off
+    serial.capture_output(false);
+    start(&mut serial, b'i', 0x81);
+    serial.step(512 * 8);
+    assert!(serial.take_output().is_empty());
+}
+
+// Writer which can be looked at after it's been handed to Serial
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// Verify bytes are copied to the tee as soon as they're sent
+#[test]
+fn test_tee() {
+    let buf = Arc::new(Mutex::new(Vec::new()));
+    let mut serial = Serial::new();
+    serial.set_tee(Some(Box::new(SharedBuf(Arc::clone(&buf)))));
+
+    for &c in b"ok\n" {
+        start(&mut serial, c, 0x81);
+        serial.step(512 * 8);
+    }
+    assert_eq!(*buf.lock().unwrap(), b"ok\n");
+
+    // nothing is sent without the internal clock
+    start(&mut serial, b'x', 0x80);
+    serial.step(512 * 8);
+    assert_eq!(*buf.lock().unwrap(), b"ok\n");
+}
+
+// Verify the fast clock is only available in CGB mode
+#[test]
+fn test_fast_clock() {
+    let mut serial = Serial::new();
+
+    start(&mut serial, 0x00, 0x83);
+    assert_eq!(serial.mem_read_byte(SC), 0xff);
+    serial.step(16 * 8);
+    assert!(!serial.check_and_consume_int_req());
+    serial.step(512 * 8);
+    assert!(serial.check_and_consume_int_req());
+
+    serial.set_cgb(true);
+    start(&mut serial, 0x00, 0x83);
+    assert_eq!(serial.mem_read_byte(SC), 0xff);
+    serial.step(16 * 8);
+    assert!(serial.check_and_consume_int_req());
+    assert_eq!(serial.mem_read_byte(SC), 0x7f);
+}
+
+// Verify nothing happens waiting on an external clock with nothing attached
+#[test]
+fn test_external_clock() {
+    let mut serial = Serial::new();
+
+    start(&mut serial, 0x42, 0x80);
+    assert_eq!(serial.mem_read_byte(SC), 0xfe);
+
+    serial.step(512 * 8 * 4);
+    assert_eq!(serial.mem_read_byte(SC), 0xfe);
+    assert_eq!(serial.mem_read_byte(SB), 0x42);
+    assert!(!serial.check_and_consume_int_req());
+    assert!(serial.take_output().is_empty());
+}
+
+// Verify bytes are exchanged with the link cable in both directions
+#[test]
+fn test_link_cable() {
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let incoming = Arc::new(Mutex::new(Vec::new()));
+    let replies = Arc::new(Mutex::new(Vec::new()));
+
+    let mut serial = Serial::new();
+    serial.set_link_cable(Box::new(TestLink {
+        sent: Arc::clone(&sent),
+        incoming: Arc::clone(&incoming),
+        replies: Arc::clone(&replies),
+        answer: 0x5a,
+    }));
+
+    // we clock the transfer
+    start(&mut serial, 0x12, 0x81);
+    serial.step(512 * 8);
+    assert!(serial.check_and_consume_int_req());
+    assert_eq!(serial.mem_read_byte(SB), 0x5a);
+    assert_eq!(*sent.lock().unwrap(), vec![0x12]);
+
+    // the other end clocks a transfer before we're ready for it
+    incoming.lock().unwrap().push(0x34);
+    serial.step(4);
+    assert!(!serial.check_and_consume_int_req());
+    assert_eq!(serial.mem_read_byte(SB), 0x5a);
+
+    // and again once we are
+    start(&mut serial, 0x56, 0x80);
+    incoming.lock().unwrap().push(0x78);
+    serial.step(4);
+    assert!(serial.check_and_consume_int_req());
+    assert_eq!(serial.mem_read_byte(SB), 0x78);
+    assert_eq!(serial.mem_read_byte(SC), 0x7e);
+    assert_eq!(*replies.lock().unwrap(), vec![0xff, 0x56]);
+    assert_eq!(*sent.lock().unwrap(), vec![0x12]);
+}
+
+// Run a transfer between two linked serial ports, a clocking b
+fn link_transfer(a: &mut Serial, b: &mut Serial) {
+    start(b, 0xbb, 0x80);
+    start(a, 0xaa, 0x81);
+
+    for _ in 0..10000 {
+        a.step(4);
+        b.step(4);
+
+        if a.check_int_req() && b.check_int_req() {
+            break;
+        }
+    }
+
+    assert!(a.check_and_consume_int_req());
+    assert!(b.check_and_consume_int_req());
+    assert_eq!(a.mem_read_byte(SB), 0xbb);
+    assert_eq!(b.mem_read_byte(SB), 0xaa);
+}
+
+// Verify two serial ports exchange bytes over a loopback link
+#[test]
+fn test_peer_loopback() {
+    let (link_a, link_b) = PeerLink::loopback();
+    let mut a = Serial::new();
+    let mut b = Serial::new();
+    a.set_link_cable(Box::new(link_a));
+    b.set_link_cable(Box::new(link_b));
+
+    link_transfer(&mut a, &mut b);
+    link_transfer(&mut b, &mut a);
+
+    // the clocking end waits for the other end to answer
+    start(&mut a, 0x11, 0x81);
+    a.step(512 * 8 * 2);
+    assert_eq!(a.mem_read_byte(SC), 0xff);
+    b.step(4);
+    a.step(4);
+    assert!(a.check_and_consume_int_req());
+    assert_eq!(a.mem_read_byte(SB), 0xff);
+
+    // and gets 1s if the other end goes away
+    start(&mut a, 0x22, 0x81);
+    drop(b);
+    a.step(512 * 8);
+    assert!(a.check_and_consume_int_req());
+    assert_eq!(a.mem_read_byte(SB), 0xff);
+}
+
+// Verify two serial ports exchange bytes over a unix socket, even with a
+// stale socket left at the path, and that the socket is cleaned up
+#[cfg(unix)]
+#[test]
+fn test_peer_unix_socket() {
+    let path = std::env::temp_dir().join(format!("dookieboy-link-{}.sock", std::process::id()));
+    let path = path.to_string_lossy().into_owned();
+    std::fs::remove_file(&path).ok();
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+    let listen_path = path.clone();
+    let listener = std::thread::spawn(move || PeerLink::unix_listen(&listen_path).unwrap());
+
+    let mut link_b = None;
+    for _ in 0..100 {
+        if let Ok(link) = PeerLink::unix_connect(&path) {
+            link_b = Some(link);
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let mut a = Serial::new();
+    let mut b = Serial::new();
+    a.set_link_cable(Box::new(listener.join().unwrap()));
+    b.set_link_cable(Box::new(link_b.expect("unable to connect")));
+    assert!(!std::path::Path::new(&path).exists());
+
+    // packets take a moment to cross the socket
+    start(&mut b, 0xbb, 0x80);
+    start(&mut a, 0xaa, 0x81);
+    for _ in 0..1000 {
+        a.step(512 * 8);
+        b.step(4);
+
+        if a.check_int_req() && b.check_int_req() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    assert_eq!(a.mem_read_byte(SB), 0xbb);
+    assert_eq!(b.mem_read_byte(SB), 0xaa);
+}
@@ -17,8 +17,8 @@ use std::io::{
     Write,
 };
 
-use crate::gameboy::Gameboy;
-use crate::memory::Memory;
+use dookieboy::Gameboy;
+use dookieboy::Memory;
 
 const PROMPT: &str = "dookie>";
 
@@ -71,7 +71,13 @@ impl Shell {
                         }
 
                         for _ in 0..num_steps {
-                            gb.cpu().step();
+                            let was_locked = gb.locked();
+                            gb.step();
+
+                            if gb.locked() && !was_locked {
+                                println!("CPU locked up by illegal instruction");
+                                Shell::dump_the_dookie(gb);
+                            }
 
                             if self.dump_mode {
                                 Shell::dump_the_dookie(gb);
@@ -124,6 +130,9 @@ impl Shell {
                         }
                     },
                     "q" | "e" | "quit" | "exit" => {
+                        if let Err(e) = gb.save() {
+                            println!("unable to write save file: {}", e);
+                        }
                         std::process::exit(0);
                     },
                     _ => {
@@ -143,6 +152,6 @@ impl Shell {
     }
 
     fn dump_the_dookie(gb: &mut Gameboy) {
-        println!("CPU:\n{}", gb.cpu());
+        println!("CPU:\n{}", gb.cpu_state());
     }
 }
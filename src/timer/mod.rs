@@ -0,0 +1,174 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use crate::int_src::InterruptSource;
+use crate::memory::Memory;
+
+const TAC_ENABLE: u8 = 2;
+const TAC_CLOCK_MASK: u8 = 0x3;
+
+/*
+ * Timer
+ *
+ * DIV is the upper byte of a free running 16 bit counter which is
+ * incremented every T-cycle. TIMA is not clocked directly, instead it is
+ * incremented on the falling edge of (counter bit selected by TAC) AND
+ * (TAC enable). This means anything that makes that signal go low will
+ * increment TIMA, including writes to DIV and TAC.
+ *
+ * When TIMA overflows it reads as 0x00 for one M-cycle, after which it is
+ * reloaded from TMA and the interrupt is requested.
+ * https://gbdev.io/pandocs/Timer_Obscure_Behaviour.html
+ */
+pub struct Timer {
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    overflow_pending: bool,
+    reloading: bool,
+    int_req: bool,
+}
+
+impl InterruptSource for Timer {
+    fn check_int_req(&self) -> bool {
+        self.int_req
+    }
+
+    fn consume_int_req(&mut self) {
+        self.int_req = false;
+    }
+}
+
+impl Memory for Timer {
+    fn mem_read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0xff04 => (self.counter >> 8) as u8,
+            0xff05 => self.tima,
+            0xff06 => self.tma,
+            0xff07 => 0xf8 | self.tac,
+            _ => panic!("read from invalid address: {:#06x}", addr),
+        }
+    }
+
+    fn mem_write_byte(&mut self, addr: u16, val: u8) {
+        match addr {
+            0xff04 => self.reset_div(),
+            0xff05 => {
+                // writing TIMA in the cycle after an overflow cancels the
+                // reload, writing it in the reload cycle is ignored
+                if !self.reloading {
+                    self.tima = val;
+                    self.overflow_pending = false;
+                }
+            },
+            0xff06 => {
+                // writing TMA in the reload cycle also lands in TIMA
+                self.tma = val;
+                if self.reloading {
+                    self.tima = val;
+                }
+            },
+            0xff07 => {
+                let old_signal = self.signal();
+                self.tac = val & 0x7;
+                self.check_falling_edge(old_signal);
+            },
+            _ => panic!("write to invalid address: {:#06x}", addr),
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Timer {
+        Timer::new()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Timer {
+        Timer {
+            // default DMG powerup state according to
+            // https://gbdev.io/pandocs/Power_Up_Sequence.html#hardware-registers
+            counter: 0xabcc,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            overflow_pending: false,
+            reloading: false,
+            int_req: false,
+        }
+    }
+
+    // advance the timer by the given number of T-cycles. the timer is
+    // clocked once per M-cycle so this should be a multiple of 4
+    pub fn step(&mut self, cycles: usize) {
+        for _ in 0..(cycles / 4) {
+            self.tick();
+        }
+    }
+
+    pub fn reset_div(&mut self) {
+        let old_signal = self.signal();
+        self.counter = 0;
+        self.check_falling_edge(old_signal);
+    }
+
+    fn tick(&mut self) {
+        self.reloading = false;
+
+        if self.overflow_pending {
+            self.overflow_pending = false;
+            self.reloading = true;
+            self.tima = self.tma;
+            self.int_req = true;
+        }
+
+        let old_signal = self.signal();
+        self.counter = self.counter.wrapping_add(4);
+        self.check_falling_edge(old_signal);
+    }
+
+    // counter bit that clocks TIMA for each TAC clock select value:
+    // 4096Hz, 262144Hz, 65536Hz, 16384Hz
+    fn clock_bit(&self) -> u8 {
+        match self.tac & TAC_CLOCK_MASK {
+            0 => 9,
+            1 => 3,
+            2 => 5,
+            _ => 7,
+        }
+    }
+
+    fn signal(&self) -> bool {
+        ((self.tac & (1 << TAC_ENABLE)) != 0) && ((self.counter >> self.clock_bit()) & 1) == 1
+    }
+
+    fn check_falling_edge(&mut self, old_signal: bool) {
+        if old_signal && !self.signal() {
+            self.increment_tima();
+        }
+    }
+
+    fn increment_tima(&mut self) {
+        let (tima, overflow) = self.tima.overflowing_add(1);
+        self.tima = tima;
+
+        if overflow {
+            self.overflow_pending = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
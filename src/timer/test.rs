@@ -0,0 +1,137 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+use super::*;
+
+const DIV: u16 = 0xff04;
+const TIMA: u16 = 0xff05;
+const TMA: u16 = 0xff06;
+const TAC: u16 = 0xff07;
+
+// Build a timer with the internal counter cleared
+fn new_timer() -> Timer {
+    let mut timer = Timer::new();
+    timer.mem_write_byte(DIV, 0);
+    timer
+}
+
+// Verify DIV increments every 256 T-cycles and is reset by writes
+#[test]
+fn test_div() {
+    let mut timer = new_timer();
+
+    timer.step(252);
+    assert_eq!(timer.mem_read_byte(DIV), 0);
+    timer.step(4);
+    assert_eq!(timer.mem_read_byte(DIV), 1);
+
+    timer.step(256 * 0x10);
+    assert_eq!(timer.mem_read_byte(DIV), 0x11);
+
+    timer.mem_write_byte(DIV, 0xa5);
+    assert_eq!(timer.mem_read_byte(DIV), 0);
+}
+
+// Verify TIMA increments at each of the TAC frequencies
+#[test]
+fn test_tima_frequencies() {
+    for (tac, period) in [(0x4, 1024), (0x5, 16), (0x6, 64), (0x7, 256)].iter() {
+        let mut timer = new_timer();
+        timer.mem_write_byte(TAC, *tac);
+
+        timer.step(period - 4);
+        assert_eq!(timer.mem_read_byte(TIMA), 0, "tac {:#04x}", tac);
+        timer.step(4);
+        assert_eq!(timer.mem_read_byte(TIMA), 1, "tac {:#04x}", tac);
+        timer.step(period * 9);
+        assert_eq!(timer.mem_read_byte(TIMA), 10, "tac {:#04x}", tac);
+    }
+
+    // disabled timer never increments
+    let mut timer = new_timer();
+    timer.mem_write_byte(TAC, 0x1);
+    timer.step(4096);
+    assert_eq!(timer.mem_read_byte(TIMA), 0);
+}
+
+// Verify the TMA reload and interrupt are delayed by one M-cycle
+#[test]
+fn test_overflow_reload() {
+    let mut timer = new_timer();
+    timer.mem_write_byte(TMA, 0xa0);
+    timer.mem_write_byte(TIMA, 0xff);
+    timer.mem_write_byte(TAC, 0x5);
+
+    timer.step(16);
+    assert_eq!(timer.mem_read_byte(TIMA), 0x00);
+    assert!(!timer.check_int_req());
+
+    timer.step(4);
+    assert_eq!(timer.mem_read_byte(TIMA), 0xa0);
+    assert!(timer.check_and_consume_int_req());
+    assert!(!timer.check_int_req());
+}
+
+// Verify writing TIMA in the cycle after overflow cancels the reload
+#[test]
+fn test_overflow_cancel() {
+    let mut timer = new_timer();
+    timer.mem_write_byte(TMA, 0xa0);
+    timer.mem_write_byte(TIMA, 0xff);
+    timer.mem_write_byte(TAC, 0x5);
+
+    timer.step(16);
+    timer.mem_write_byte(TIMA, 0x12);
+    timer.step(4);
+    assert_eq!(timer.mem_read_byte(TIMA), 0x12);
+    assert!(!timer.check_int_req());
+}
+
+// Verify writes during the reload cycle: TIMA is ignored, TMA is copied
+#[test]
+fn test_overflow_reload_cycle_writes() {
+    let mut timer = new_timer();
+    timer.mem_write_byte(TMA, 0xa0);
+    timer.mem_write_byte(TIMA, 0xff);
+    timer.mem_write_byte(TAC, 0x5);
+
+    timer.step(20);
+    timer.mem_write_byte(TIMA, 0x12);
+    assert_eq!(timer.mem_read_byte(TIMA), 0xa0);
+
+    timer.mem_write_byte(TMA, 0x34);
+    assert_eq!(timer.mem_read_byte(TIMA), 0x34);
+}
+
+// Verify DIV and TAC writes increment TIMA on a falling edge
+#[test]
+fn test_falling_edge_glitches() {
+    let mut timer = new_timer();
+    timer.mem_write_byte(TAC, 0x5);
+
+    // bit 3 of the counter is set, resetting DIV drops it
+    timer.step(8);
+    assert_eq!(timer.mem_read_byte(TIMA), 0);
+    timer.mem_write_byte(DIV, 0);
+    assert_eq!(timer.mem_read_byte(TIMA), 1);
+
+    // disabling the timer while bit 3 is set drops the signal too
+    timer.step(8);
+    timer.mem_write_byte(TAC, 0x1);
+    assert_eq!(timer.mem_read_byte(TIMA), 2);
+
+    // re-enabling with the selected bit clear does not increment
+    timer.mem_write_byte(DIV, 0);
+    timer.mem_write_byte(TAC, 0x5);
+    assert_eq!(timer.mem_read_byte(TIMA), 2);
+}
@@ -0,0 +1,101 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+// The library API as seen by a frontend, everything from the crate root
+
+mod common;
+
+use dookieboy::{Button, Gameboy, Memory, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const P1_START: u8 = 1 << 3;
+
+// ld a,0x10; ldh (P1),a
+// loop: ldh a,(P1); ld (0xc000),a; jr loop
+const P1_PROGRAM: [u8; 11] = [
+    0x3e, 0x10, 0xe0, 0x00,
+    0xf0, 0x00, 0xea, 0x00, 0xc0, 0x18, 0xf9,
+];
+
+fn boot(program: &[u8]) -> Gameboy {
+    let mut gb = Gameboy::new();
+    gb.load_rom_data(common::rom::program_rom(program)).unwrap();
+    gb.reset();
+    gb
+}
+
+// Verify a ROM can be loaded from bytes and bad ones are rejected
+#[test]
+fn load_rom_data() {
+    let mut gb = Gameboy::new();
+    assert!(gb.load_rom_data(vec![0; 0x100]).is_err());
+
+    let mut rom = common::rom::program_rom(&[]);
+    rom[0x104] ^= 0xff;
+    assert!(gb.load_rom_data(rom).is_err());
+
+    assert!(gb.load_rom_data(common::rom::program_rom(&[])).is_ok());
+}
+
+// Verify running with nothing loaded doesn't fall over
+#[test]
+fn no_rom() {
+    let mut gb = Gameboy::new();
+    gb.reset();
+    gb.run_frame();
+    assert_eq!(gb.mem_read_byte(0x0100), 0xff);
+}
+
+// Verify frames are reported and the framebuffer is the size of the screen
+#[test]
+fn run_frame() {
+    let mut gb = boot(&P1_PROGRAM);
+
+    let frames = (0..10).filter(|_| {
+        gb.run_frame();
+        gb.check_and_consume_frame()
+    }).count();
+    assert!(frames >= 9);
+    assert_eq!(gb.framebuffer().len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+    assert!(gb.framebuffer().iter().all(|&shade| shade < 4));
+}
+
+// Verify button presses are seen by the running program
+#[test]
+fn buttons() {
+    let mut gb = boot(&P1_PROGRAM);
+
+    gb.run_frame();
+    assert_ne!(gb.mem_read_byte(0xc000) & P1_START, 0);
+
+    gb.update_button(Button::START, true);
+    gb.run_frame();
+    assert_eq!(gb.mem_read_byte(0xc000) & P1_START, 0);
+
+    gb.update_button(Button::START, false);
+    gb.run_frame();
+    assert_ne!(gb.mem_read_byte(0xc000) & P1_START, 0);
+}
+
+// Verify memory writes land where the CPU can see them
+#[test]
+fn memory() {
+    let mut gb = boot(&P1_PROGRAM);
+
+    gb.mem_write_byte(0xc100, 0xa5);
+    gb.mem_write_word_le(0xc102, 0x1234);
+    gb.run_frame();
+    assert_eq!(gb.mem_read_byte(0xc100), 0xa5);
+    assert_eq!(gb.mem_read_byte(0xe100), 0xa5);
+    assert_eq!(gb.mem_read_word_le(0xc102), 0x1234);
+}
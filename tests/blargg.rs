@@ -0,0 +1,111 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+// Blargg's test ROMs print their results over serial, finishing with
+// "Passed" or "Failed". Expected layout under DOOKIEBOY_TEST_ROMS matches
+// the gb-test-roms repo, e.g. cpu_instrs/individual/01-special.gb
+
+mod common;
+
+// the full cpu_instrs ROM takes about a minute of emulated time
+const MAX_FRAMES: usize = 60 * 120;
+
+fn run_blargg(rel: &str) {
+    let mut gb = common::boot(rel);
+
+    let output = common::run_until_serial(&mut gb, MAX_FRAMES, |out| {
+        out.contains("Passed") || out.contains("Failed")
+    });
+
+    assert!(output.contains("Passed") && !output.contains("Failed"),
+            "{} did not pass:\n{}", rel, output);
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs() {
+    run_blargg("cpu_instrs/cpu_instrs.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_01_special() {
+    run_blargg("cpu_instrs/individual/01-special.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_02_interrupts() {
+    run_blargg("cpu_instrs/individual/02-interrupts.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_03_op_sp_hl() {
+    run_blargg("cpu_instrs/individual/03-op sp,hl.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_04_op_r_imm() {
+    run_blargg("cpu_instrs/individual/04-op r,imm.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_05_op_rp() {
+    run_blargg("cpu_instrs/individual/05-op rp.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_06_ld_r_r() {
+    run_blargg("cpu_instrs/individual/06-ld r,r.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_07_jr_jp_call_ret_rst() {
+    run_blargg("cpu_instrs/individual/07-jr,jp,call,ret,rst.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_08_misc_instrs() {
+    run_blargg("cpu_instrs/individual/08-misc instrs.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_09_op_r_r() {
+    run_blargg("cpu_instrs/individual/09-op r,r.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_10_bit_ops() {
+    run_blargg("cpu_instrs/individual/10-bit ops.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn cpu_instrs_11_op_a_hl() {
+    run_blargg("cpu_instrs/individual/11-op a,(hl).gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn instr_timing() {
+    run_blargg("instr_timing/instr_timing.gb");
+}
@@ -0,0 +1,103 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+// Shared helpers for running test ROMs headlessly. ROMs aren't checked in,
+// so the tests that need them are ignored by default. Point
+// DOOKIEBOY_TEST_ROMS at a directory containing them and ask for them:
+//
+//   DOOKIEBOY_TEST_ROMS=~/gb-test-roms cargo test --release -- --ignored
+//
+// A ROM that can't be found is then a failure, not a skip.
+
+// each test crate only uses some of these
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use dookieboy::Gameboy;
+
+pub mod rom;
+
+pub const ROM_DIR_VAR: &str = "DOOKIEBOY_TEST_ROMS";
+
+// resolve a ROM path relative to the test ROM directory
+pub fn rom_path(rel: &str) -> PathBuf {
+    let dir = env::var_os(ROM_DIR_VAR)
+        .unwrap_or_else(|| panic!("{} isn't set, see tests/common/mod.rs", ROM_DIR_VAR));
+    let path = PathBuf::from(dir).join(rel);
+
+    assert!(path.is_file(), "{} not found", path.display());
+    path
+}
+
+// boot a ROM from the test ROM directory without a display
+pub fn boot(rel: &str) -> Gameboy {
+    boot_file(&rom_path(rel))
+}
+
+pub fn boot_file(path: &Path) -> Gameboy {
+    let mut gb = Gameboy::new();
+    gb.load_rom(path.to_string_lossy().into_owned())
+        .unwrap_or_else(|e| panic!("unable to load {}: {}", path.display(), e));
+    gb.reset();
+
+    gb
+}
+
+// write program_rom(program) to a temporary file, returning its path
+pub fn write_rom(name: &str, program: &[u8]) -> PathBuf {
+    let path = env::temp_dir().join(format!("dookieboy-{}-{}.gb", name, process::id()));
+    fs::write(&path, rom::program_rom(program)).unwrap();
+    path
+}
+
+// step until the CPU hits an LD B,B breakpoint or max_cycles M-cycles
+// have passed, returns whether the breakpoint was hit
+pub fn run_until_breakpoint(gb: &mut Gameboy, max_cycles: usize) -> bool {
+    gb.set_ld_b_b_breakpoint(true);
+
+    let mut cycles = 0;
+    while cycles < max_cycles {
+        cycles += gb.step();
+
+        if gb.check_and_consume_breakpoint() {
+            return true;
+        }
+    }
+
+    false
+}
+
+// run frames until done() is happy with the serial output so far, or the
+// frame limit runs out. returns everything printed over serial
+pub fn run_until_serial<F>(gb: &mut Gameboy, max_frames: usize, done: F) -> String
+    where F: Fn(&str) -> bool
+{
+    let mut output = Vec::new();
+    gb.capture_serial_output(true);
+
+    for _ in 0..max_frames {
+        gb.run_frame();
+        output.extend(gb.take_serial_output());
+
+        if done(&String::from_utf8_lossy(&output)) {
+            break;
+        }
+    }
+
+    String::from_utf8_lossy(&output).into_owned()
+}
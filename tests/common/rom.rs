@@ -0,0 +1,75 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+// ROM images for tests, both the integration tests here and the cartridge
+// unit tests, which include this file with #[path]. It isn't built into
+// the library, so it can't use anything from the crate.
+
+// the unit tests only build blank ROMs
+#![allow(dead_code)]
+
+// same as the cartridge's, which the integration tests can't see
+const NINTENDO_LOGO: [u8; 48] = [
+    0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b,
+    0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00, 0x0d,
+    0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e,
+    0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99,
+    0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc,
+    0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e
+];
+
+const ENTRY_POINT: usize = 0x100;
+const PROGRAM_START: usize = 0x150;
+
+// A blank ROM image with a valid header for the given cartridge type and
+// size codes
+pub fn build_rom(cart_type: u8, rom_size_code: u8, ram_size_code: u8) -> Vec<u8> {
+    let mut rom = vec![0; 32768 << rom_size_code];
+
+    rom[0x104..0x134].copy_from_slice(&NINTENDO_LOGO);
+    rom[0x147] = cart_type;
+    rom[0x148] = rom_size_code;
+    rom[0x149] = ram_size_code;
+    finish_rom(&mut rom);
+    rom
+}
+
+// A 32KiB ROM only cartridge which runs program from 0x150
+pub fn program_rom(program: &[u8]) -> Vec<u8> {
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+
+    // nop; jp 0x150
+    rom[ENTRY_POINT..ENTRY_POINT + 4].copy_from_slice(&[0x00, 0xc3, 0x50, 0x01]);
+    rom[PROGRAM_START..PROGRAM_START + program.len()].copy_from_slice(program);
+    finish_rom(&mut rom);
+    rom
+}
+
+// Fill in header and global checksums, after changing anything
+pub fn finish_rom(rom: &mut [u8]) {
+    let mut header_checksum: u8 = 0;
+    for byte in &rom[0x134..=0x14c] {
+        header_checksum = header_checksum.wrapping_sub(*byte).wrapping_sub(1);
+    }
+    rom[0x14d] = header_checksum;
+
+    let mut cart_checksum: u16 = 0;
+    for (i, byte) in rom.iter().enumerate() {
+        if (i != 0x14e) && (i != 0x14f) {
+            cart_checksum = cart_checksum.wrapping_add(*byte as u16);
+        }
+    }
+    rom[0x14e] = (cart_checksum >> 8) as u8;
+    rom[0x14f] = (cart_checksum & 0xff) as u8;
+}
@@ -0,0 +1,114 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+// The dookieboy binary run without a display
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::process::{self, Command};
+
+use dookieboy::{PALETTE, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// ld a,0xff; ldh (BGP),a; done: jr done
+const BLACK_PROGRAM: [u8; 6] = [0x3e, 0xff, 0xe0, 0x47, 0x18, 0xfe];
+
+// ld a,0x0a; ld (0x0000),a; ld (0xa000),a; done: jr done
+const SAVE_PROGRAM: [u8; 10] = [0x3e, 0x0a, 0xea, 0x00, 0x00, 0xea, 0x00, 0xa0, 0x18, 0xfe];
+
+fn dookieboy(args: &[&str]) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_dookieboy"));
+    cmd.args(args);
+    cmd
+}
+
+// Verify the screen is dumped as a PPM after the requested frames
+#[test]
+fn screenshot() {
+    let rom = common::write_rom("headless", &BLACK_PROGRAM);
+    let out = env::temp_dir().join(format!("dookieboy-headless-{}.ppm", process::id()));
+
+    let status = dookieboy(&["--headless", "--frames", "10", "--out",
+                             out.to_str().unwrap(), rom.to_str().unwrap()])
+        .status()
+        .unwrap();
+    let image = fs::read(&out);
+    fs::remove_file(&rom).ok();
+    fs::remove_file(&out).ok();
+
+    assert!(status.success());
+    let image = image.unwrap();
+    let header = format!("P6\n{} {}\n255\n", SCREEN_WIDTH, SCREEN_HEIGHT);
+    assert!(image.starts_with(header.as_bytes()));
+
+    let pixels = &image[header.len()..];
+    assert_eq!(pixels.len(), SCREEN_WIDTH * SCREEN_HEIGHT * 3);
+    assert!(pixels.chunks(3).all(|rgb| rgb == PALETTE[3]));
+}
+
+// Verify a missing frame count, unwritable output or options which don't
+// go together are an error
+#[test]
+fn failure_status() {
+    let rom = common::write_rom("headless-fail", &BLACK_PROGRAM);
+    let rom_path = rom.to_str().unwrap();
+
+    let no_frames = dookieboy(&["--headless", rom_path]).output().unwrap();
+    let bad_out = dookieboy(&["--headless", "--frames", "1", "--out",
+                              "/nonexistent/dookieboy.ppm", rom_path])
+        .output()
+        .unwrap();
+    let no_headless = dookieboy(&["--frames", "1", rom_path]).output().unwrap();
+    let out_only = dookieboy(&["--out", "/tmp/dookieboy.ppm", rom_path]).output().unwrap();
+    // -d only exists in debug builds
+    let with_debug = cfg!(debug_assertions).then(|| {
+        dookieboy(&["-d", "--headless", "--frames", "1", rom_path]).output().unwrap()
+    });
+    fs::remove_file(&rom).ok();
+
+    assert_eq!(no_frames.status.code(), Some(1));
+    assert_eq!(bad_out.status.code(), Some(1));
+    assert_eq!(no_headless.status.code(), Some(1));
+    assert_eq!(out_only.status.code(), Some(1));
+    if let Some(with_debug) = with_debug {
+        assert_eq!(with_debug.status.code(), Some(1));
+    }
+}
+
+// Verify a battery backed game which writes its RAM leaves no .sav behind
+#[test]
+fn no_save() {
+    let rom = common::write_rom("headless-save", &SAVE_PROGRAM);
+    let out = rom.with_extension("ppm");
+    let sav = rom.with_extension("sav");
+
+    // MBC1+RAM+BATTERY with 8KiB of RAM
+    let mut data = fs::read(&rom).unwrap();
+    data[0x147] = 0x03;
+    data[0x149] = 0x02;
+    common::rom::finish_rom(&mut data);
+    fs::write(&rom, data).unwrap();
+
+    let status = dookieboy(&["--headless", "--frames", "1", rom.to_str().unwrap()])
+        .status()
+        .unwrap();
+    let saved = sav.exists();
+    fs::remove_file(&rom).ok();
+    fs::remove_file(&out).ok();
+    fs::remove_file(&sav).ok();
+
+    assert!(status.success());
+    assert!(!saved);
+}
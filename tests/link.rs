@@ -0,0 +1,74 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+// Two emulators in the same process joined by a loopback link cable
+
+mod common;
+
+use std::fs;
+
+use dookieboy::Register8Bit;
+use dookieboy::Gameboy;
+use dookieboy::PeerLink;
+
+// ld a,SB; ldh (SB),a; ld a,SC; ldh (SC),a
+// wait: ldh a,(SC); bit 7,a; jp nz,wait
+// ldh a,(SB); ld b,a
+// done: ld b,b; jp done
+fn transfer_program(sb: u8, sc: u8) -> Vec<u8> {
+    vec![
+        0x3e, sb, 0xe0, 0x01, 0x3e, sc, 0xe0, 0x02,
+        0xf0, 0x02, 0xcb, 0x7f, 0xc2, 0x58, 0x01,
+        0xf0, 0x01, 0x47,
+        0x40, 0xc3, 0x62, 0x01,
+    ]
+}
+
+fn boot(name: &str, program: &[u8]) -> Gameboy {
+    let path = common::write_rom(name, program);
+    let mut gb = common::boot_file(&path);
+    fs::remove_file(path).ok();
+
+    gb.set_ld_b_b_breakpoint(true);
+    gb
+}
+
+// Verify a byte is swapped each way between the clocking and clocked ends
+#[test]
+fn loopback_transfer() {
+    let mut master = boot("link-master", &transfer_program(0x12, 0x81));
+    let mut slave = boot("link-slave", &transfer_program(0x34, 0x80));
+
+    let (master_link, slave_link) = PeerLink::loopback();
+    master.set_link_cable(Box::new(master_link));
+    slave.set_link_cable(Box::new(slave_link));
+
+    // give the slave a head start so it's waiting when the master clocks
+    let mut slave_done = false;
+    let mut master_done = false;
+    for _ in 0..60 {
+        slave.run_frame();
+        master.run_frame();
+
+        slave_done |= slave.check_and_consume_breakpoint();
+        master_done |= master.check_and_consume_breakpoint();
+        if slave_done && master_done {
+            break;
+        }
+    }
+
+    assert!(master_done && slave_done, "transfer never finished");
+    assert_eq!(master.reg(Register8Bit::B), 0x34);
+    assert_eq!(slave.reg(Register8Bit::B), 0x12);
+}
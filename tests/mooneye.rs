@@ -0,0 +1,153 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+// Mooneye's test ROMs finish by executing LD B,B. On success the registers
+// hold the first few Fibonacci numbers, on failure they're all 0x42.
+// Expected layout under DOOKIEBOY_TEST_ROMS matches the mooneye-test-suite
+// build output, e.g. mooneye/acceptance/ei_sequence.gb
+
+mod common;
+
+use dookieboy::Register8Bit;
+
+// Gameboy::step only ticks the peripherals once per instruction, so the
+// tests which time accesses within an instruction (add_sp_e_timing,
+// call_timing, di_timing, div_timing, ei_timing, halt_ime0_nointr_timing,
+// halt_ime1_timing, intr_timing, reti_intr_timing, ...) can't pass yet and
+// are left out until the CPU ticks the rest of the system every M-cycle
+
+// acceptance tests finish well within 10 seconds of emulated time
+const MAX_CYCLES: usize = 1048576 * 10;
+
+const SIGNATURE_REGS: [Register8Bit; 6] = [
+    Register8Bit::B,
+    Register8Bit::C,
+    Register8Bit::D,
+    Register8Bit::E,
+    Register8Bit::H,
+    Register8Bit::L,
+];
+const PASS_SIGNATURE: [u8; 6] = [3, 5, 8, 13, 21, 34];
+
+fn run_mooneye(rel: &str) {
+    let mut gb = common::boot(&format!("mooneye/{}", rel));
+
+    assert!(common::run_until_breakpoint(&mut gb, MAX_CYCLES),
+            "{} timed out without reaching LD B,B", rel);
+
+    let regs: Vec<u8> = SIGNATURE_REGS.iter().map(|&r| gb.reg(r)).collect();
+    assert_eq!(regs, PASS_SIGNATURE, "{} failed\n{}", rel, gb.cpu_state());
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn ei_sequence() {
+    run_mooneye("acceptance/ei_sequence.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn halt_ime0_ei() {
+    run_mooneye("acceptance/halt_ime0_ei.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn if_ie_registers() {
+    run_mooneye("acceptance/if_ie_registers.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn rapid_di_ei() {
+    run_mooneye("acceptance/rapid_di_ei.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn bits_mem_oam() {
+    run_mooneye("acceptance/bits/mem_oam.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn bits_reg_f() {
+    run_mooneye("acceptance/bits/reg_f.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn instr_daa() {
+    run_mooneye("acceptance/instr/daa.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn interrupts_ie_push() {
+    run_mooneye("acceptance/interrupts/ie_push.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn oam_dma_basic() {
+    run_mooneye("acceptance/oam_dma/basic.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn timer_div_write() {
+    run_mooneye("acceptance/timer/div_write.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn timer_tim00() {
+    run_mooneye("acceptance/timer/tim00.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn timer_tim01() {
+    run_mooneye("acceptance/timer/tim01.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn timer_tim10() {
+    run_mooneye("acceptance/timer/tim10.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn timer_tim11() {
+    run_mooneye("acceptance/timer/tim11.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn timer_tima_reload() {
+    run_mooneye("acceptance/timer/tima_reload.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn timer_tima_write_reloading() {
+    run_mooneye("acceptance/timer/tima_write_reloading.gb");
+}
+
+#[test]
+#[ignore = "needs DOOKIEBOY_TEST_ROMS"]
+fn timer_tma_write_reloading() {
+    run_mooneye("acceptance/timer/tma_write_reloading.gb");
+}
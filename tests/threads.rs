@@ -0,0 +1,56 @@
+// ░░░░░░░░░░░█▀▀░░█░░░░░░
+// ░░░░░░▄▀▀▀▀░░░░░█▄▄░░░░
+// ░░░░░░█░█░░░░░░░░░░▐░░░
+// ░░░░░░▐▐░░░░░░░░░▄░▐░░░
+// ░░░░░░█░░░░░░░░▄▀▀░▐░░░
+// ░░░░▄▀░░░░░░░░▐░▄▄▀░░░░
+// ░░▄▀░░░▐░░░░░█▄▀░▐░░░░░
+// ░░█░░░▐░░░░░░░░▄░█░░░░░
+// ░░░█▄░░▀▄░░░░▄▀▐░█░░░░░
+// ░░░█▐▀▀▀░▀▀▀▀░░▐░█░░░░░
+// ░░▐█▐▄░░▀░░░░░░▐░█▄▄░░
+// ░░░▀▀░▄TSM▄░░░▐▄▄▄▀░░░
+
+
+// Independent emulators running side by side on their own threads
+
+mod common;
+
+use std::fs;
+use std::thread;
+
+use dookieboy::Register8Bit;
+
+const INSTANCES: u8 = 8;
+
+// ld a,0; ld c,n
+// loop: add a,c; dec c; jr nz,loop
+// ld b,a; done: ld b,b; jr done
+fn sum_program(n: u8) -> Vec<u8> {
+    vec![
+        0x3e, 0x00, 0x0e, n,
+        0x81, 0x0d, 0x20, 0xfc,
+        0x47, 0x40, 0x18, 0xfe,
+    ]
+}
+
+// Verify each instance can be moved to a thread and runs to completion
+#[test]
+fn parallel_instances() {
+    let handles: Vec<_> = (1..=INSTANCES).map(|i| {
+        let n = i * 10;
+        let path = common::write_rom(&format!("threads-{}", i), &sum_program(n));
+        let mut gb = common::boot_file(&path);
+        fs::remove_file(path).ok();
+
+        thread::spawn(move || {
+            assert!(common::run_until_breakpoint(&mut gb, 1_000_000));
+            (n, gb.reg(Register8Bit::B))
+        })
+    }).collect();
+
+    for handle in handles {
+        let (n, sum) = handle.join().unwrap();
+        assert_eq!(sum, ((n as usize) * (n as usize + 1) / 2) as u8);
+    }
+}